@@ -0,0 +1,71 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use c::{eval, EvalError, State};
+
+/// Inputs longer than this are evaluated in the background, shorter ones
+/// are quicker to evaluate than to hand over.
+pub const LONG_INPUT: usize = 4096;
+
+type Job = (u64, String, State);
+type Done = (u64, State, Option<EvalError>, Duration);
+
+/// Evaluates input on a thread of its own, so typing into a long input
+/// doesn't wait for every evaluation. Only the newest input counts: queued
+/// ones are skipped and results for older ones dropped.
+pub struct Evaluator {
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+    latest: u64,
+    pending: bool,
+}
+
+impl Evaluator {
+    pub fn new() -> Evaluator {
+        let (jobs, queue) = channel::<Job>();
+        let (finished, done) = channel();
+        thread::spawn(move || {
+            while let Ok(mut job) = queue.recv() {
+                while let Ok(newer) = queue.try_recv() {
+                    job = newer;
+                }
+                let (n, input, mut state) = job;
+                let start = Instant::now();
+                let err = eval(&input, &mut state).err();
+                if finished.send((n, state, err, start.elapsed())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Evaluator { jobs, done, latest: 0, pending: false }
+    }
+
+    pub fn start(&mut self, input: String, base: State) {
+        self.latest += 1;
+        self.pending = self.jobs.send((self.latest, input, base)).is_ok();
+    }
+
+    /// Forget the evaluation in progress, if any.
+    pub fn cancel(&mut self) {
+        self.latest += 1;
+        self.pending = false;
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// The result of the newest input, and how long it took, once it is
+    /// ready.
+    pub fn poll(&mut self) -> Option<(State, Option<EvalError>, Duration)> {
+        while let Ok((n, state, err, took)) = self.done.try_recv() {
+            if n == self.latest && self.pending {
+                self.pending = false;
+                return Some((state, err, took));
+            }
+        }
+        None
+    }
+}
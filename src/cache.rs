@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+use c::{eval_from, EvalError, State};
+
+/// Evaluating for longer than this since the last checkpoint makes
+/// another one.
+const WORTH_KEEPING: Duration = Duration::from_millis(1);
+
+/// What evaluating the words of the last input left, at the points where
+/// getting there took a while, so evaluating it again as it is typed into
+/// only evaluates from the last word before the change.
+#[derive(Default)]
+pub struct Cache {
+    input: String,
+    /// Where in `input` a word ends, the state after it and how long
+    /// evaluating up to it took, in order.
+    checkpoints: Vec<(usize, State, Duration)>,
+}
+
+impl Cache {
+    /// Forget everything, for when what evaluation starts from changes.
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+    }
+
+    /// Evaluate `input` on top of `base`, like [`c::eval`], and how long
+    /// that takes all the way through: the time to the checkpoint it went
+    /// on from is counted too.
+    pub fn eval(&mut self, input: &str, base: &State) -> (State, Option<EvalError>, Duration) {
+        // a checkpoint holds while the input up to it is the same and the
+        // word it's after hasn't grown
+        let same = self.input.bytes().zip(input.bytes()).take_while(|(a, b)| a == b).count();
+        let valid = self.checkpoints.iter()
+            .take_while(|&&(end, ..)| end <= same && input[end..].chars().next().map_or(true, char::is_whitespace))
+            .count();
+        self.checkpoints.truncate(valid);
+        self.input = input.to_owned();
+
+        let (from, mut state, mut took) = match self.checkpoints.last() {
+            Some((end, state, took)) => (*end, state.clone(), *took),
+            None => (0, base.clone(), Duration::ZERO),
+        };
+        let checkpoints = &mut self.checkpoints;
+        let mut since = Instant::now();
+        let err = eval_from(input, from, &mut state, |end, state| {
+            // not inside quotes, where what follows could join the word
+            if since.elapsed() >= WORTH_KEEPING && input[..end].matches('"').count() % 2 == 0 {
+                // without the time keeping the checkpoint takes
+                took += since.elapsed();
+                checkpoints.push((end, state.clone(), took));
+                since = Instant::now();
+            }
+        }).err();
+        (state, err, took + since.elapsed())
+    }
+}
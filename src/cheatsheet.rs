@@ -0,0 +1,71 @@
+use std::fmt::Write;
+
+pub const FORMATS: &[&str] = &["text", "markdown"];
+
+/// The sections of the cheatsheet: a title and its (name, description)
+/// rows, straight from the evaluator's tables.
+fn sections() -> Vec<(&'static str, Vec<(String, &'static str)>)> {
+    let words = |dot: bool| {
+        c::WORDS.iter()
+            .filter(|w| w.names[0].starts_with('.') == dot)
+            .map(|w| {
+                let mut name = w.names.join(" ");
+                for arg in w.args() {
+                    write!(name, " <{arg}>").unwrap();
+                }
+                (name, w.help)
+            })
+            .collect()
+    };
+
+    vec![
+        ("Syntax", c::SYNTAX.iter().map(|&(example, help)| (example.to_owned(), help)).collect()),
+        ("Operators", words(false)),
+        ("Dot commands", words(true)),
+        ("Constants", c::CONSTANTS.iter().map(|&(name, _, help)| (name.to_owned(), help)).collect()),
+    ]
+}
+
+/// The plain text cheatsheet, also what `.help` and F1 show.
+pub fn text() -> String {
+    let sections = sections();
+    let mut out = String::new();
+
+    let width = sections.iter().flat_map(|(_, rows)| rows).map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (title, rows) in &sections {
+        writeln!(out, "{title}").unwrap();
+        for (name, help) in rows {
+            writeln!(out, "  {name:width$}  {help}").unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+
+    out
+}
+
+/// `c cheatsheet [text|markdown]`
+pub fn render(format: &str) -> Result<String, String> {
+    let sections = sections();
+    let mut out = String::new();
+
+    match format {
+        "text" => out = text(),
+        "markdown" => {
+            // code spans can't hold a `|` inside a table cell
+            let cell = |s: &str| s.replace('|', "\\|");
+            writeln!(out, "# c cheatsheet").unwrap();
+            for (title, rows) in &sections {
+                writeln!(out, "\n## {title}\n").unwrap();
+                writeln!(out, "| Word | Description |").unwrap();
+                writeln!(out, "|-|-|").unwrap();
+                for (name, help) in rows {
+                    let names = name.split(' ').map(|n| format!("`{}`", cell(n))).collect::<Vec<_>>().join(" ");
+                    writeln!(out, "| {names} | {} |", cell(help)).unwrap();
+                }
+            }
+        },
+        _ => return Err(format!("unknown format '{format}', expected one of {}", FORMATS.join(", "))),
+    }
+
+    Ok(out)
+}
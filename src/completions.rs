@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use c::Value;
+
+type Constants = BTreeMap<String, Value>;
+
+/// What follows a flag on the command line.
+enum Arg {
+    None,
+    File,
+    Text,
+    Choice(&'static [&'static str]),
+}
+
+// keep in sync with parse_args
+const FLAGS: &[(&str, Arg, &str)] = &[
+    ("--sink", Arg::Text, "send committed results to cmd:, file: or http://"),
+    ("--no-sinks", Arg::None, "don't send results to the configured sinks"),
+    ("--auto-session", Arg::None, "restore the session saved on exit, and save it again"),
+    ("--no-history", Arg::None, "don't read or write the history file"),
+    ("--full-screen", Arg::None, "use the whole terminal, with a scrollable log"),
+    ("--yes", Arg::None, "don't ask before discarding the stack or a session"),
+    ("--no-color", Arg::None, "don't use colors, also with NO_COLOR set"),
+    ("--accumulate", Arg::None, "start each line from the stack the last one left"),
+    ("--trace", Arg::None, "show the stack after every word"),
+    ("--sandbox", Arg::None, "refuse words that read or write files"),
+    ("--bench", Arg::Text, "evaluate an expression a number of times, and time it"),
+    ("--serve", Arg::File, "answer JSON-RPC requests on a unix socket"),
+    ("--from-json", Arg::File, "push numbers from a JSON file"),
+    ("--select", Arg::Text, "JSON pointer to the numbers to push"),
+    ("--from-csv", Arg::File, "push a column of a CSV file"),
+    ("--col", Arg::Text, "CSV column to push, counting from 1"),
+    ("--count", Arg::Text, "gen: number of evaluations"),
+    ("--seed", Arg::Text, "gen: random seed"),
+    ("--format", Arg::Choice(&["plain", "csv", "json"]), "gen: output format"),
+];
+
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+// subcommands, with the choices for the argument that follows them
+const COMMANDS: &[(&str, &[&str], &str)] = &[
+    ("gen", &[], "evaluate an expression repeatedly"),
+    ("completions", SHELLS, "print a shell completion script"),
+    ("cheatsheet", crate::cheatsheet::FORMATS, "print a reference of every word"),
+    ("tutor", &[], "learn c in a few lessons"),
+];
+
+/// Words and constants worth completing, with those from the config:
+/// single characters like `+` are quicker typed than completed.
+fn words(constants: &Constants) -> impl Iterator<Item = (&str, &str)> {
+    c::WORDS.iter()
+        .flat_map(|w| w.names.iter().map(|&name| (name, w.help)))
+        .chain(c::CONSTANTS.iter().map(|&(name, _, help)| (name, help)))
+        .chain(constants.keys().map(|name| (name.as_str(), "a constant from the config")))
+        .filter(|(name, _)| name.len() > 1)
+}
+
+/// `c completions <shell>`
+pub fn script(shell: &str, constants: &Constants) -> Result<String, String> {
+    match shell {
+        "bash" => Ok(bash(constants)),
+        "zsh" => Ok(zsh(constants)),
+        "fish" => Ok(fish(constants)),
+        _ => Err(format!("unknown shell '{shell}', expected one of {}", SHELLS.join(", "))),
+    }
+}
+
+fn bash(constants: &Constants) -> String {
+    let mut out = String::new();
+    let names = |f: fn(&Arg) -> bool| {
+        FLAGS.iter().filter(|(_, arg, _)| f(arg)).map(|(name, ..)| *name).collect::<Vec<_>>().join("|")
+    };
+
+    writeln!(out, "_c() {{").unwrap();
+    writeln!(out, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"").unwrap();
+    writeln!(out, "    case \"$prev\" in").unwrap();
+    writeln!(out, "        {}) COMPREPLY=($(compgen -f -- \"$cur\")); return;;", names(|a| matches!(a, Arg::File))).unwrap();
+    writeln!(out, "        {}) return;;", names(|a| matches!(a, Arg::Text))).unwrap();
+    for (name, arg, _) in FLAGS {
+        if let Arg::Choice(choices) = arg {
+            writeln!(out, "        {name}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return;;", choices.join(" ")).unwrap();
+        }
+    }
+    writeln!(out, "    esac").unwrap();
+    for (name, choices, _) in COMMANDS.iter().filter(|c| !c.1.is_empty()) {
+        writeln!(out, "    if [ \"$COMP_CWORD\" -eq 2 ] && [ \"$prev\" = {name} ]; then").unwrap();
+        writeln!(out, "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return", choices.join(" ")).unwrap();
+        writeln!(out, "    fi").unwrap();
+    }
+    writeln!(out, "    case \"$cur\" in").unwrap();
+    writeln!(out, "        --*) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"));;", FLAGS.iter().map(|f| f.0).collect::<Vec<_>>().join(" ")).unwrap();
+    writeln!(out, "        *) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", words(constants).map(|w| w.0).collect::<Vec<_>>().join(" ")).unwrap();
+    writeln!(out, "           [ \"$COMP_CWORD\" -eq 1 ] && COMPREPLY+=($(compgen -W \"{}\" -- \"$cur\"));;", COMMANDS.iter().map(|c| c.0).collect::<Vec<_>>().join(" ")).unwrap();
+    writeln!(out, "    esac").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "complete -F _c c").unwrap();
+
+    out
+}
+
+fn zsh(constants: &Constants) -> String {
+    // _describe takes name:description, so colons in names need escaping
+    let describe = |items: &mut dyn Iterator<Item = (&str, &str)>| {
+        items.map(|(name, help)| format!("'{}:{}'", name.replace(':', "\\:"), help.replace('\'', "'\\''")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut out = String::new();
+    writeln!(out, "#compdef c").unwrap();
+    writeln!(out, "_c() {{").unwrap();
+    writeln!(out, "    local -a flags cwords commands").unwrap();
+    writeln!(out, "    flags=({})", describe(&mut FLAGS.iter().map(|(name, _, help)| (*name, *help)))).unwrap();
+    writeln!(out, "    cwords=({})", describe(&mut words(constants))).unwrap();
+    writeln!(out, "    commands=({})", describe(&mut COMMANDS.iter().map(|&(name, _, help)| (name, help)))).unwrap();
+    writeln!(out, "    case $words[CURRENT-1] in").unwrap();
+    for (name, arg, _) in FLAGS {
+        match arg {
+            Arg::None => {},
+            Arg::File => writeln!(out, "        {name}) _files; return;;").unwrap(),
+            Arg::Text => writeln!(out, "        {name}) return;;").unwrap(),
+            Arg::Choice(choices) => writeln!(out, "        {name}) compadd {}; return;;", choices.join(" ")).unwrap(),
+        }
+    }
+    writeln!(out, "    esac").unwrap();
+    for (name, choices, _) in COMMANDS.iter().filter(|c| !c.1.is_empty()) {
+        writeln!(out, "    if (( CURRENT == 3 )) && [[ $words[2] == {name} ]]; then compadd {}; return; fi", choices.join(" ")).unwrap();
+    }
+    writeln!(out, "    if [[ $PREFIX == --* ]]; then _describe flag flags; return; fi").unwrap();
+    writeln!(out, "    (( CURRENT == 2 )) && _describe command commands").unwrap();
+    writeln!(out, "    _describe word cwords").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out, "compdef _c c").unwrap();
+
+    out
+}
+
+fn fish(constants: &Constants) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+
+    let mut out = String::new();
+    writeln!(out, "complete -c c -f").unwrap();
+    for (name, choices, help) in COMMANDS {
+        writeln!(out, "complete -c c -n __fish_use_subcommand -a {name} -d {}", quote(help)).unwrap();
+        if !choices.is_empty() {
+            writeln!(out, "complete -c c -n '__fish_seen_subcommand_from {name}' -a {}", quote(&choices.join(" "))).unwrap();
+        }
+    }
+    for (name, arg, help) in FLAGS {
+        let arg = match arg {
+            Arg::None => String::new(),
+            Arg::File => " -r -F".into(),
+            Arg::Text => " -x".into(),
+            Arg::Choice(choices) => format!(" -x -a {}", quote(&choices.join(" "))),
+        };
+        writeln!(out, "complete -c c -l {}{arg} -d {}", name.trim_start_matches("--"), quote(help)).unwrap();
+    }
+    for (name, help) in words(constants) {
+        writeln!(out, "complete -c c -n 'not __fish_seen_subcommand_from completions cheatsheet tutor' -a {} -d {}", quote(name), quote(help)).unwrap();
+    }
+
+    out
+}
@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+
+use c::{State, TokenKind, Value};
+
+use crate::keys::Keymap;
+use crate::sink::Sink;
+use crate::theme::Theme;
+
+/// Settings from `$XDG_CONFIG_HOME/c/config` (or `~/.config/c/config`).
+///
+/// The file is a list of `key = value` lines, `#` starts a comment. Keys
+/// that take a list (like `sink`) may be repeated.
+pub struct Config {
+    pub sinks: Vec<Sink>,
+    /// Lines kept in the history file, 0 turns it off.
+    pub history_size: usize,
+    /// `stack_top = first` shows the top of the stack on the left.
+    pub top_first: bool,
+    /// `height = <rows>` takes that many rows rather than 3, and with more
+    /// than 3 shows the stack a value per row.
+    pub height: u16,
+    /// `confirm = false` commits lines that discard state without asking.
+    pub confirm: bool,
+    /// `sandbox = true` always runs as with `--sandbox`.
+    pub sandbox: bool,
+    /// `accumulate = true` always runs as with `--accumulate`.
+    pub accumulate: bool,
+    /// `key.<action> = <key> ..` lines, see [`Keymap`].
+    pub keys: Keymap,
+    /// `color.<part> = <color>` lines, see [`Theme`].
+    pub theme: Theme,
+    /// `const.<name> = <input>` lines, the value the input evaluates to.
+    pub constants: Vec<(String, Value)>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            sinks: vec![],
+            history_size: 1000,
+            top_first: false,
+            height: 3,
+            confirm: true,
+            sandbox: false,
+            accumulate: false,
+            keys: Keymap::default(),
+            theme: Theme::default(),
+            constants: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = path() else {
+            return Ok(Config::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(src) => Config::parse(&src).map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    fn parse(src: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        for (n, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", n + 1));
+            };
+            let value = value.trim();
+
+            match key.trim() {
+                "sink" => config.sinks.push(value.parse().map_err(|e| format!("line {}: {e}", n + 1))?),
+                "history_size" => {
+                    config.history_size = value.parse().map_err(|_| format!("line {}: history_size needs a number", n + 1))?;
+                },
+                "stack_top" => {
+                    config.top_first = match value {
+                        "first" => true,
+                        "last" => false,
+                        _ => return Err(format!("line {}: stack_top needs 'first' or 'last'", n + 1)),
+                    };
+                },
+                "height" => {
+                    config.height = value.parse().ok().filter(|&rows| rows >= 3)
+                        .ok_or(format!("line {}: height needs a number of rows, at least 3", n + 1))?;
+                },
+                "confirm" => {
+                    config.confirm = value.parse().map_err(|_| format!("line {}: confirm needs 'true' or 'false'", n + 1))?;
+                },
+                "sandbox" => {
+                    config.sandbox = value.parse().map_err(|_| format!("line {}: sandbox needs 'true' or 'false'", n + 1))?;
+                },
+                "accumulate" => {
+                    config.accumulate = value.parse().map_err(|_| format!("line {}: accumulate needs 'true' or 'false'", n + 1))?;
+                },
+                key if key.starts_with("key.") => {
+                    config.keys.bind(&key["key.".len()..], value).map_err(|e| format!("line {}: {e}", n + 1))?;
+                },
+                key if key.starts_with("color.") => {
+                    config.theme.set(&key["color.".len()..], value).map_err(|e| format!("line {}: {e}", n + 1))?;
+                },
+                key if key.starts_with("const.") => {
+                    let constant = constant(&key["const.".len()..], value).map_err(|e| format!("line {}: {e}", n + 1))?;
+                    config.constants.push(constant);
+                },
+                key => return Err(format!("line {}: unknown key '{key}'", n + 1)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// `const.<name> = <input>`, like `const.rate = 0.21`.
+fn constant(name: &str, input: &str) -> Result<(String, Value), String> {
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("'{name}' can't be the name of a constant, it needs letters, digits and _, starting with a letter"));
+    }
+    if c::tokens(name, &State::default()).iter().any(|token| token.kind != TokenKind::Unknown) {
+        return Err(format!("'{name}' already means something"));
+    }
+    let mut state = State::default();
+    match c::eval(input, &mut state).map_err(|e| format!("{name}: {e}"))?[..] {
+        [value] => Ok((name.to_owned(), value)),
+        _ => Err(format!("{name}: '{input}' has to leave exactly one value")),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/c` (or `~/.config/c`).
+pub fn dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("c"))
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dir()?.join("config"))
+}
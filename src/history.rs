@@ -0,0 +1,60 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Committed input lines, oldest first, kept in `$XDG_DATA_HOME/c/history`
+/// (or `~/.local/share/c/history`) one per line.
+#[derive(Default)]
+pub struct History {
+    pub lines: Vec<String>,
+    // None when history isn't kept on disk
+    path: Option<PathBuf>,
+    size: usize,
+    // lines in the file, which can be more than `lines` holds
+    on_disk: usize,
+}
+
+impl History {
+    /// Read the history file, keeping at most `size` lines. A size of 0
+    /// keeps no history file, only the lines of this session.
+    pub fn load(size: usize) -> Result<History, String> {
+        let Some(path) = crate::session::data_dir().map(|dir| dir.join("history")).filter(|_| size > 0) else {
+            // still recall this session's lines
+            return Ok(History { size: usize::MAX, ..History::default() });
+        };
+        let mut lines: Vec<String> = match std::fs::read_to_string(&path) {
+            Ok(src) => src.lines().map(str::to_owned).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        let on_disk = lines.len();
+        lines.drain(..lines.len().saturating_sub(size));
+
+        Ok(History { lines, path: Some(path), size, on_disk })
+    }
+
+    /// Add a committed line, in memory and on disk.
+    pub fn push(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim();
+        if line.is_empty() || self.lines.last().is_some_and(|last| last == line) {
+            return Ok(());
+        }
+        self.lines.push(line.to_owned());
+
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        self.lines.drain(..self.lines.len().saturating_sub(self.size));
+        if self.on_disk >= self.size {
+            // drop the oldest lines from the file too
+            std::fs::write(path, self.lines.iter().map(|l| l.clone() + "\n").collect::<String>())?;
+            self.on_disk = self.lines.len();
+        } else {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{line}")?;
+            self.on_disk += 1;
+        }
+        Ok(())
+    }
+}
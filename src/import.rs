@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use c::{parse_number, Value};
+
+/// Numbers read from a file onto the stack before evaluating, from
+/// `--from-json <path> [--select <pointer>]` or `--from-csv <path> [--col N]`.
+pub enum Import {
+    // `pointer` is a JSON pointer (RFC 6901), "" is the whole document
+    Json { path: PathBuf, pointer: String },
+    // `col` counts from 1, like cut and awk
+    Csv { path: PathBuf, col: usize },
+}
+
+impl Import {
+    pub fn read(&self) -> Result<Vec<Value>, String> {
+        match self {
+            Import::Json { path, pointer } => {
+                let src = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+                let doc: serde_json::Value = serde_json::from_str(&src)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                let selected = doc.pointer(pointer)
+                    .ok_or_else(|| format!("{}: nothing at '{pointer}'", path.display()))?;
+
+                match selected {
+                    serde_json::Value::Array(xs) => xs.iter().map(json_number).collect(),
+                    x => Ok(vec![json_number(x)?]),
+                }
+                .map_err(|e| format!("{}: {e}", path.display()))
+            },
+            Import::Csv { path, col } => {
+                let src = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+                let mut values = vec![];
+                for (n, line) in src.lines().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let Some(cell) = line.split(',').nth(col - 1) else {
+                        return Err(format!("{}:{}: no column {col}", path.display(), n + 1));
+                    };
+                    let cell = cell.trim().trim_matches('"');
+                    match parse_number(cell) {
+                        Some(x) => values.push(x),
+                        // a header
+                        None if n == 0 => {},
+                        None => return Err(format!("{}:{}: '{cell}' is not a number", path.display(), n + 1)),
+                    }
+                }
+                Ok(values)
+            },
+        }
+    }
+}
+
+fn json_number(x: &serde_json::Value) -> Result<Value, String> {
+    let serde_json::Value::Number(num) = x else {
+        return Err(format!("{x} is not a number"));
+    };
+    match num.as_i64() {
+        Some(num) => Ok(Value::Int(num)),
+        // Number displays floats as the shortest text that round trips,
+        // which parses back with the same error bound as the original.
+        // Exponents aren't literal syntax, those are taken as exact.
+        None => parse_number(&num.to_string())
+            .or(num.as_f64().map(|x| Value::Float(x, 0.0)))
+            .ok_or_else(|| format!("{num} is out of range")),
+    }
+}
@@ -0,0 +1,121 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The TUI actions that can be bound to other keys.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Action {
+    Quit,
+    Commit,
+    Copy,
+    HistoryPrev,
+    HistoryNext,
+    Help,
+    Undo,
+    Redo,
+    NextStack,
+}
+
+impl Action {
+    const ALL: &'static [(&'static str, Action)] = &[
+        ("quit", Action::Quit),
+        ("commit", Action::Commit),
+        ("copy", Action::Copy),
+        ("history_prev", Action::HistoryPrev),
+        ("history_next", Action::HistoryNext),
+        ("help", Action::Help),
+        ("undo", Action::Undo),
+        ("redo", Action::Redo),
+        ("next_stack", Action::NextStack),
+    ];
+}
+
+/// What the keys do, changed with `key.<action> = <key> ..` lines in the
+/// config, like `key.quit = Esc C-d`.
+pub struct Keymap {
+    bindings: Vec<(Action, KeyCode, KeyModifiers)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let ctrl = KeyModifiers::CONTROL;
+        let none = KeyModifiers::NONE;
+        Keymap {
+            bindings: vec![
+                (Action::Quit, KeyCode::Char('d'), ctrl),
+                (Action::Commit, KeyCode::Enter, none),
+                (Action::Copy, KeyCode::Char('y'), ctrl),
+                (Action::HistoryPrev, KeyCode::Up, none),
+                (Action::HistoryNext, KeyCode::Down, none),
+                (Action::Help, KeyCode::F(1), none),
+                (Action::Undo, KeyCode::Char('z'), ctrl),
+                (Action::Redo, KeyCode::Char('r'), ctrl),
+                (Action::NextStack, KeyCode::Char('n'), ctrl),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// Bind `action` to `keys` instead of what it was bound to.
+    pub fn bind(&mut self, action: &str, keys: &str) -> Result<(), String> {
+        let Some(&(_, action)) = Action::ALL.iter().find(|(name, _)| *name == action) else {
+            let names = Action::ALL.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            return Err(format!("unknown action '{action}', expected one of {}", names.join(", ")));
+        };
+        let keys = keys.split_whitespace().map(parse_key).collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("expected a key, like C-d or Esc".into());
+        }
+        self.bindings.retain(|binding| binding.0 != action);
+        self.bindings.extend(keys.into_iter().map(|(code, modifiers)| (action, code, modifiers)));
+        Ok(())
+    }
+
+    pub fn action(&self, key: KeyEvent) -> Option<Action> {
+        // whether shift was held is already in the character
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        self.bindings.iter()
+            .find(|&&(_, code, mods)| code == key.code && mods == modifiers)
+            .map(|binding| binding.0)
+    }
+}
+
+/// `C-d`, `M-x`, `S-Tab`, `Esc`, `F1`, `q` ..
+fn parse_key(key: &str) -> Result<(KeyCode, KeyModifiers), String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = key;
+    while let Some((modifier, name)) = rest.split_once('-').filter(|(_, name)| !name.is_empty()) {
+        modifiers |= match modifier {
+            "C" => KeyModifiers::CONTROL,
+            "M" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier '{modifier}' in '{key}', expected C, M or S")),
+        };
+        rest = name;
+    }
+
+    let code = match rest {
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Space" => KeyCode::Char(' '),
+        _ if rest.chars().count() == 1 => KeyCode::Char(rest.chars().next().unwrap()),
+        _ => match rest.strip_prefix('F').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=24) => KeyCode::F(n),
+            _ => return Err(format!("unknown key '{key}'")),
+        },
+    };
+    Ok((code, modifiers))
+}
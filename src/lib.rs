@@ -0,0 +1,2817 @@
+#![feature(let_chains)]
+
+//! The evaluator behind `c`, an RPN calculator.
+//!
+//! ```
+//! let mut state = c::State::default();
+//! let stack = c::eval("1 2 + 4 m", &mut state).unwrap();
+//! assert_eq!(stack, [c::Value::Int(12)]);
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+fn operator(op: &str) -> Option<Op> {
+    match op {
+        "p" | "+" => Some(Op::Add),
+        "-" | "−" => Some(Op::Sub),
+        "m" | "*" | "×" => Some(Op::Mul),
+        "d" | "÷" => Some(Op::Div),
+        "%" => Some(Op::Rem),
+        _ => None,
+    }
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "d",
+            Op::Rem => "%",
+        }
+    }
+
+    /// `exact` divides integers to a [`Value::Ratio`] rather than
+    /// truncating, for `.q`.
+    fn apply(self, a: Value, b: Value, exact: bool) -> Result<Value, EvalError> {
+        if matches!(a, Value::Cidr(..)) || matches!(b, Value::Cidr(..)) {
+            return Err(EvalError::new(format!("{} needs numbers, not a subnet", self.name())));
+        }
+        if matches!(a, Value::Quantity(..)) || matches!(b, Value::Quantity(..)) {
+            return apply_quantity(self, a, b);
+        }
+        if let (Value::Int(a), Value::Int(b)) = (a.num(), b.num()) {
+            let op = match self {
+                Op::Add => i64::wrapping_add,
+                Op::Sub => i64::wrapping_sub,
+                Op::Mul => i64::wrapping_mul,
+                Op::Div => i64::wrapping_div,
+                Op::Rem => i64::wrapping_rem,
+            };
+            if b == 0 && matches!(self, Op::Div | Op::Rem) {
+                return Err(EvalError::new("division by zero"));
+            }
+            if exact && self == Op::Div {
+                return ratio(a as i128, b as i128);
+            }
+            return Ok(Value::Int(op(a, b)));
+        }
+        if let (Some((an, ad)), Some((bn, bd))) = (a.as_ratio(), b.as_ratio()) {
+            return match self {
+                Op::Add => ratio(an * bd + bn * ad, ad * bd),
+                Op::Sub => ratio(an * bd - bn * ad, ad * bd),
+                Op::Mul => ratio(an * bn, ad * bd),
+                Op::Div => ratio(an * bd, ad * bn),
+                Op::Rem if bn == 0 => Err(EvalError::new("division by zero")),
+                Op::Rem => ratio(an * bd % (bn * ad), ad * bd),
+            };
+        }
+
+        let (a, ea) = a.to_float();
+        let (b, eb) = b.to_float();
+        // propagated input error plus the exact rounding error of the
+        // operation itself (two-sum for + and -, fma residuals for * and d)
+        let res = match self {
+            Op::Add | Op::Sub => {
+                let b = if self == Op::Sub { -b } else { b };
+                let res = a + b;
+                let bb = res - a;
+                let round = (a - (res - bb)) + (b - bb);
+                Value::Float(res, ea + eb + round.abs())
+            },
+            Op::Mul => {
+                let res = a * b;
+                let round = a.mul_add(b, -res);
+                Value::Float(res, a.abs() * eb + b.abs() * ea + ea * eb + round.abs())
+            },
+            Op::Div => {
+                let res = a / b;
+                let round = (-res).mul_add(b, a) / b;
+                // |a/b - (a+da)/(b+db)| <= (|a|eb + |b|ea) / (|b| (|b| - eb))
+                let err = if b.abs() > eb {
+                    (a.abs() * eb + b.abs() * ea) / (b.abs() * (b.abs() - eb))
+                } else {
+                    f64::INFINITY
+                };
+                Value::Float(res, err + round.abs())
+            },
+            // fmod is exact, only the inputs' error carries over
+            Op::Rem => Value::Float(a % b, ea + eb * (a / b).abs().ceil()),
+        };
+
+        Ok(res)
+    }
+}
+
+/// Distance from `x` to the next float away from zero.
+fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+    if !x.is_finite() {
+        return f64::NAN;
+    }
+    f64::from_bits(x.to_bits() + 1) - x
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Value {
+    Int(i64),
+    /// The value and a bound on the rounding error accumulated computing it.
+    Float(f64, f64),
+    /// 1 or 0 from a comparison or predicate, which arithmetic treats as
+    /// an integer.
+    Bool(bool),
+    /// An amount of a unit, like `500 MB`.
+    Quantity(f64, &'static Unit),
+    /// An IPv4 address and the length of its network prefix, `10.0.0.0/8`.
+    Cidr(u32, u8),
+    /// An exact fraction `1/3`, in lowest terms with a denominator above 1,
+    /// from dividing integers in `.q`.
+    Ratio(i64, i64),
+}
+
+impl Value {
+    /// The number a boolean stands for, other values as they are.
+    fn num(self) -> Value {
+        match self {
+            Value::Bool(b) => Value::Int(b as i64),
+            x => x,
+        }
+    }
+
+    fn to_float(self) -> (f64, f64) {
+        match self.num() {
+            Value::Int(x) => {
+                let f = x as f64;
+                (f, (f as i128 - x as i128).unsigned_abs() as f64)
+            },
+            Value::Float(x, err) => (x, err),
+            Value::Bool(_) => unreachable!(),
+            Value::Quantity(x, _) => (x, 0.0),
+            Value::Cidr(addr, _) => (addr as f64, 0.0),
+            Value::Ratio(n, d) => {
+                // rounding n, d and the division
+                let f = n as f64 / d as f64;
+                (f, f.abs() * f64::EPSILON * 1.5)
+            },
+        }
+    }
+
+    /// An integer or fraction as a numerator and denominator.
+    fn as_ratio(self) -> Option<(i128, i128)> {
+        match self.num() {
+            Value::Int(x) => Some((x as i128, 1)),
+            Value::Ratio(n, d) => Some((n as i128, d as i128)),
+            _ => None,
+        }
+    }
+}
+
+/// `n/d` in lowest terms, or an integer if `d` divides it.
+fn ratio(n: i128, d: i128) -> Result<Value, EvalError> {
+    if d == 0 {
+        return Err(EvalError::new("division by zero"));
+    }
+    let (mut a, mut b) = (n.unsigned_abs(), d.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    let (n, d) = (n / a as i128 * d.signum(), d.abs() / a as i128);
+    match (i64::try_from(n), i64::try_from(d)) {
+        (Ok(n), Ok(1)) => Ok(Value::Int(n)),
+        (Ok(n), Ok(d)) => Ok(Value::Ratio(n, d)),
+        _ => Err(EvalError::new("the fraction doesn't fit in 64 bits")),
+    }
+}
+
+/// A unit a [`Value::Quantity`] can be in, see [`UNITS`].
+#[derive(PartialEq, Debug)]
+pub struct Unit {
+    pub name: &'static str,
+    /// What it measures, as powers of bytes and seconds: `[0, -1]` is a
+    /// frequency.
+    dim: [i8; 2],
+    /// How many of the first unit of the same dimension it is.
+    scale: f64,
+}
+
+const fn unit(name: &'static str, dim: [i8; 2], scale: f64) -> Unit {
+    Unit { name, dim, scale }
+}
+
+/// Every unit, the first of each dimension is what the others are
+/// converted through and what computed quantities are shown in.
+pub static UNITS: &[Unit] = &[
+    unit("B", [1, 0], 1.0),
+    unit("kB", [1, 0], 1e3),
+    unit("MB", [1, 0], 1e6),
+    unit("GB", [1, 0], 1e9),
+    unit("TB", [1, 0], 1e12),
+    unit("KiB", [1, 0], (1u64 << 10) as f64),
+    unit("MiB", [1, 0], (1u64 << 20) as f64),
+    unit("GiB", [1, 0], (1u64 << 30) as f64),
+    unit("TiB", [1, 0], (1u64 << 40) as f64),
+    unit("s", [0, 1], 1.0),
+    unit("ns", [0, 1], 1e-9),
+    unit("us", [0, 1], 1e-6),
+    unit("ms", [0, 1], 1e-3),
+    unit("min", [0, 1], 60.0),
+    unit("h", [0, 1], 3600.0),
+    unit("day", [0, 1], 86400.0),
+    unit("Hz", [0, -1], 1.0),
+    unit("kHz", [0, -1], 1e3),
+    unit("MHz", [0, -1], 1e6),
+    unit("GHz", [0, -1], 1e9),
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.name == name)
+}
+
+/// `op` on values of which at least one is a quantity. `+` and `%` need the
+/// same dimension on both sides and give the unit of `a`, `*` and `d`
+/// multiply the dimensions.
+fn apply_quantity(op: Op, a: Value, b: Value) -> Result<Value, EvalError> {
+    // in the first unit of the dimension
+    let base = |x: Value| match x.num() {
+        Value::Quantity(x, unit) => (x * unit.scale, Some(unit)),
+        x => (x.to_float().0, None),
+    };
+    let ((a, unit_a), (b, unit_b)) = (base(a), base(b));
+    let dim = |unit: Option<&Unit>| unit.map_or([0, 0], |unit| unit.dim);
+    let name = |unit: Option<&Unit>| unit.map_or("a plain number", |unit| unit.name);
+
+    let (res, dim) = match op {
+        Op::Add | Op::Sub | Op::Rem => {
+            let (Some(unit), true) = (unit_a, dim(unit_a) == dim(unit_b)) else {
+                return Err(EvalError::new(format!("{} needs the same kind of unit on both sides, not {} and {}", op.name(), name(unit_a), name(unit_b))));
+            };
+            let res = match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                _ => a % b,
+            };
+            return Ok(Value::Quantity(res / unit.scale, unit));
+        },
+        Op::Mul => (a * b, [dim(unit_a)[0] + dim(unit_b)[0], dim(unit_a)[1] + dim(unit_b)[1]]),
+        Op::Div => (a / b, [dim(unit_a)[0] - dim(unit_b)[0], dim(unit_a)[1] - dim(unit_b)[1]]),
+    };
+    // scaling a quantity keeps its unit
+    match (unit_a, unit_b) {
+        (Some(unit), None) => return Ok(Value::Quantity(res / unit.scale, unit)),
+        (None, Some(unit)) if op == Op::Mul => return Ok(Value::Quantity(res / unit.scale, unit)),
+        _ => {},
+    }
+    if dim == [0, 0] {
+        return Ok(Value::Float(res, 0.0));
+    }
+    match UNITS.iter().find(|unit| unit.dim == dim) {
+        Some(unit) => Ok(Value::Quantity(res, unit)),
+        None => Err(EvalError::new(format!("there is no unit for {} {} {}", name(unit_a), op.name(), name(unit_b)))),
+    }
+}
+
+/// Parse a decimal literal like `1.5`, along with how far the nearest
+/// float is from the number that was written.
+fn parse_float(x: &str) -> Option<(f64, f64)> {
+    // 1.5, or with an exponent: 1e9, 2.5e-3
+    let (mantissa, exp) = match x.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => (mantissa, exp.parse::<i32>().ok()?),
+        None if x.contains('.') => (x, 0),
+        None => return None,
+    };
+    if !mantissa.bytes().any(|c| c.is_ascii_digit()) || !mantissa.bytes().all(|c| c.is_ascii_digit() || c == b'.' || c == b'-') {
+        return None;
+    }
+    let num: f64 = x.parse().ok().filter(|num: &f64| num.is_finite())?;
+
+    // m * 10^-n is exactly representable iff 5^n divides m (or n <= 0) and
+    // what is left fits in the 53 bit mantissa
+    let mantissa = mantissa.trim_start_matches('-');
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let fits = |m: u128| m == 0 || 128 - m.leading_zeros() - m.trailing_zeros() <= 53;
+    let m = format!("{int}{frac}").parse::<u128>().ok();
+    let exact = match frac.len() as i64 - exp as i64 {
+        n @ ..=0 => m.zip(10u128.checked_pow(n.unsigned_abs() as u32)).and_then(|(m, ten)| m.checked_mul(ten)).is_some_and(fits),
+        n => m.zip(5u128.checked_pow(n as u32)).is_some_and(|(m, five)| m % five == 0 && fits(m / five)),
+    };
+
+    Some((num, if exact { 0.0 } else { ulp(num) / 2.0 }))
+}
+
+/// `0x7fff_e3a0_1c40`, grouped from the right.
+fn addr(x: u64) -> String {
+    format!("0x{}", group(&format!("{x:x}"), 4))
+}
+
+/// `digits` in groups of `size` from the right, `_` between them.
+fn group(digits: &str, size: usize) -> String {
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % size == 0 {
+            out.push('_');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The whole part of a decimal by thousands: `-1234567.5` as
+/// `-1_234_567.5`.
+fn group_thousands(x: &str) -> String {
+    let (sign, x) = x.strip_prefix('-').map_or(("", x), |x| ("-", x));
+    let len = x.find(|c: char| !c.is_ascii_digit()).unwrap_or(x.len());
+    format!("{sign}{}{}", group(&x[..len], 3), &x[len..])
+}
+
+/// The low `bits` of `x` in binary, in groups of four.
+fn in_binary(x: u64, bits: u32) -> String {
+    group(&format!("{x:0w$b}", w = bits as usize), 4)
+}
+
+/// `x` as formatted with `{:e}` in engineering notation, with an exponent
+/// that is a multiple of three: `1.5e4` as `15e3`.
+fn engineering(x: String) -> String {
+    let (mantissa, exp) = x.split_once('e').unwrap();
+    let exp = exp.parse::<i32>().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    // move the point right by up to two digits
+    let shift = exp.rem_euclid(3) as usize;
+    let mut digits = mantissa.replace('.', "");
+    while digits.len() <= shift {
+        digits.push('0');
+    }
+    let (int, frac) = digits.split_at(shift + 1);
+    let point = if frac.is_empty() { "" } else { "." };
+    format!("{sign}{int}{point}{frac}e{}", exp - shift as i32)
+}
+
+/// `7232` as `7.1 KiB`.
+fn humanize_bytes(x: f64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let sign = if x < 0.0 { "-" } else { "" };
+    let mut n = x.abs();
+    let mut unit = 0;
+    while n >= 1024.0 && unit < UNITS.len() - 1 {
+        n /= 1024.0;
+        unit += 1;
+    }
+    let n = format!("{n:.1}");
+    format!("{sign}{} {}", n.strip_suffix(".0").unwrap_or(&n), UNITS[unit])
+}
+
+/// `4k`, `1.5M` or `64Ki`: a decimal times an SI or IEC multiple, an
+/// integer unless the multiple leaves a fraction.
+fn parse_suffixed(x: &str) -> Option<Value> {
+    const SUFFIXES: &[(&str, i128)] = &[
+        ("Ki", 1 << 10), ("Mi", 1 << 20), ("Gi", 1 << 30), ("Ti", 1 << 40), ("Pi", 1 << 50), ("Ei", 1 << 60),
+        ("k", 1_000), ("K", 1_000), ("M", 1_000_000), ("G", 1_000_000_000),
+        ("T", 1_000_000_000_000), ("P", 1_000_000_000_000_000), ("E", 1_000_000_000_000_000_000),
+    ];
+    let (num, multiple) = SUFFIXES.iter().find_map(|&(suffix, multiple)| Some((x.strip_suffix(suffix)?, multiple)))?;
+    let (int, frac) = num.split_once('.').unwrap_or((num, ""));
+    let digits = int.strip_prefix('-').unwrap_or(int);
+    if digits.is_empty() || !digits.bytes().chain(frac.bytes()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let scale = 10i128.checked_pow(frac.len() as u32)?;
+    let m = format!("{int}{frac}").parse::<i128>().ok()?.checked_mul(multiple)?;
+    if m % scale == 0 {
+        return i64::try_from(m / scale).ok().map(Value::Int);
+    }
+    let num = m as f64 / scale as f64;
+    Some(Value::Float(num, ulp(num) / 2.0))
+}
+
+/// `7:45` as minutes, 465.
+fn parse_hhmm(x: &str) -> Option<i64> {
+    let (neg, x) = match x.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, x),
+    };
+    let (h, m) = x.split_once(':')?;
+    if h.is_empty() || !h.bytes().all(|c| c.is_ascii_digit()) || m.len() != 2 || !m.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m): (i64, i64) = (h.parse().ok()?, m.parse().ok()?);
+    if m >= 60 {
+        return None;
+    }
+    let minutes = h.checked_mul(60)?.checked_add(m)?;
+    Some(if neg { -minutes } else { minutes })
+}
+
+/// `192.168.1.17`.
+fn parse_ipv4(x: &str) -> Option<u32> {
+    let parts = x.split('.').map(|part| {
+        (!part.is_empty() && part.len() <= 3 && part.bytes().all(|c| c.is_ascii_digit())).then(|| part.parse::<u8>().ok()).flatten()
+    }).collect::<Option<Vec<_>>>()?;
+    let [a, b, c, d] = parts[..] else {
+        return None;
+    };
+    Some(u32::from_be_bytes([a, b, c, d]))
+}
+
+/// `10.0.0.0/8`.
+fn parse_cidr(x: &str) -> Option<Value> {
+    let (addr, prefix) = x.split_once('/')?;
+    if prefix.is_empty() || prefix.len() > 2 || !prefix.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let prefix = prefix.parse().ok().filter(|&prefix: &u8| prefix <= 32)?;
+    Some(Value::Cidr(parse_ipv4(addr)?, prefix))
+}
+
+fn ipv4(addr: u32) -> String {
+    let [a, b, c, d] = addr.to_be_bytes();
+    format!("{a}.{b}.{c}.{d}")
+}
+
+/// `3d6`, `d20`, `1d20+4` or `4d6*`: how many dice, how many sides they
+/// have, what to add to the total, and whether to push each roll instead.
+fn parse_dice(x: &str) -> Option<(u64, u64, i64, bool)> {
+    let (x, each) = match x.strip_suffix('*') {
+        Some(x) => (x, true),
+        None => (x, false),
+    };
+    let digits = |x: &str| x.bytes().all(|c| c.is_ascii_digit()).then(|| x.parse::<u64>().ok()).flatten();
+    let (count, rest) = x.split_once('d')?;
+    let count = if count.is_empty() { 1 } else { digits(count)? };
+    let (sides, modifier) = match rest.find(['+', '-']) {
+        Some(i) => (digits(&rest[..i])?, rest[i..].parse().ok()?),
+        None => (digits(rest)?, 0),
+    };
+    (count > 0 && sides > 0).then_some((count, sides, modifier, each))
+}
+
+/// `x"deadbeef"` or `b64"aGVsbG8="` as the bytes they stand for, `None`
+/// for other words. Whitespace between the quotes is left out.
+fn parse_bytes(x: &str) -> Option<Result<Vec<u8>, EvalError>> {
+    let (hex, quoted) = match x.strip_prefix("x\"") {
+        Some(quoted) => (true, quoted),
+        None => (false, x.strip_prefix("b64\"")?),
+    };
+    let Some(quoted) = quoted.strip_suffix('"') else {
+        return Some(Err(EvalError::new(format!("{x} needs a closing \""))));
+    };
+    let digits = quoted.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    let bytes = if hex { decode_hex(&digits) } else { decode_base64(&digits) };
+    Some(bytes.ok_or_else(|| EvalError::new(format!("{x} isn't valid {}", if hex { "hex" } else { "base64" }))))
+}
+
+fn decode_hex(digits: &str) -> Option<Vec<u8>> {
+    if digits.len() % 2 != 0 || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect()
+}
+
+/// Standard or URL safe base64, the `=` padding can be left out.
+fn decode_base64(digits: &str) -> Option<Vec<u8>> {
+    let digits = digits.strip_suffix("==").or_else(|| digits.strip_suffix('=')).unwrap_or(digits);
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+    let (mut bytes, mut bits, mut n) = (vec![], 0u32, 0);
+    for c in digits.bytes() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | sextet as u32;
+        n += 6;
+        if n >= 8 {
+            n -= 8;
+            bytes.push((bits >> n) as u8);
+            bits &= (1 << n) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// `#ff8800`.
+fn parse_rgb(x: &str) -> Option<i64> {
+    let hex = x.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.bytes().all(|c| c.is_ascii_hexdigit()))?;
+    i64::from_str_radix(hex, 16).ok()
+}
+
+/// `'a'` or `U+1F600` as a code point.
+fn parse_char(x: &str) -> Option<i64> {
+    if let Some(hex) = x.strip_prefix("U+") {
+        let num = (hex.len() >= 4 && hex.bytes().all(|c| c.is_ascii_hexdigit())).then(|| u32::from_str_radix(hex, 16).ok()).flatten()?;
+        return char::from_u32(num).map(|c| c as i64);
+    }
+    let mut chars = x.strip_prefix('\'')?.strip_suffix('\'')?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c as i64),
+        _ => None,
+    }
+}
+
+/// A code point as `'a'`, or as `U+000A` when it doesn't print.
+fn character(x: i64) -> Option<String> {
+    let c = char::from_u32(u32::try_from(x).ok()?)?;
+    Some(if c.is_control() || c.is_whitespace() { format!("U+{x:04X}") } else { format!("'{c}'") })
+}
+
+const DURATION_UNITS: &[(char, i64)] = &[('d', 86400), ('h', 3600), ('m', 60), ('s', 1)];
+
+/// `2h30m` as seconds, 9000. The parts are days, hours, minutes and
+/// seconds, in that order and each at most once.
+fn parse_duration(x: &str) -> Option<i64> {
+    let (neg, mut rest) = match x.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, x),
+    };
+    let mut units = DURATION_UNITS.iter();
+    let mut seconds = 0i64;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let n: i64 = rest[..digits].parse().ok()?;
+        let suffix = rest[digits..].chars().next()?;
+        let &(_, scale) = units.by_ref().find(|&&(unit, _)| unit == suffix)?;
+        seconds = seconds.checked_add(n.checked_mul(scale)?)?;
+        rest = &rest[digits + 1..];
+    }
+    (rest.len() < x.len() - neg as usize).then_some(if neg { -seconds } else { seconds })
+}
+
+/// Seconds as `1d 2h 30m 15s`, leaving out the parts that are 0.
+fn duration(x: i64) -> String {
+    let mut rest = x.unsigned_abs();
+    let mut parts = vec![];
+    for &(unit, scale) in DURATION_UNITS {
+        let n = rest / scale as u64;
+        rest %= scale as u64;
+        if n > 0 {
+            parts.push(format!("{n}{unit}"));
+        }
+    }
+    if parts.is_empty() {
+        return "0s".into();
+    }
+    format!("{}{}", if x < 0 { "-" } else { "" }, parts.join(" "))
+}
+
+/// `2024-06-01T12:00:00Z` as unix time. The seconds, or all of the time,
+/// can be left out, and `Z` can be an offset like `+02:00` or left out for
+/// UTC too.
+fn parse_datetime(x: &str) -> Option<i64> {
+    let number = |x: &str, len: usize| (x.len() == len && x.bytes().all(|c| c.is_ascii_digit())).then(|| x.parse::<i64>().ok()).flatten();
+    let (date, time) = x.split_once('T').unwrap_or((x, "00:00"));
+    let [y, m, d] = date.splitn(3, '-').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let (y, m, d) = (number(y, 4)?, number(m, 2)?, number(d, 2)?);
+    let days = days_from_civil(y, m, d);
+    if !(1..=12).contains(&m) || civil_from_days(days) != (y, m, d) {
+        return None;
+    }
+
+    let zone = time.len().checked_sub(6).filter(|&at| matches!(time.as_bytes()[at], b'+' | b'-'));
+    let (time, offset) = match (time.strip_suffix('Z'), zone) {
+        (Some(time), _) => (time, 0),
+        (None, Some(at)) => {
+            let (time, offset) = time.split_at(at);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (h, m) = offset[1..].split_once(':')?;
+            (time, sign * (number(h, 2)? * 3600 + number(m, 2)? * 60))
+        },
+        (None, None) => (time, 0),
+    };
+    let mut parts = time.split(':');
+    let (h, m) = (number(parts.next()?, 2)?, number(parts.next()?, 2)?);
+    let s = parts.next().map_or(Some(0), |s| number(s, 2))?;
+    if parts.next().is_some() || h >= 24 || m >= 60 || s >= 60 {
+        return None;
+    }
+    Some(days * 86400 + h * 3600 + m * 60 + s - offset)
+}
+
+/// Unix time as `2024-06-01T12:00:00Z`.
+fn datetime(t: i64) -> String {
+    let (y, m, d) = civil_from_days(t.div_euclid(86400));
+    let s = t.rem_euclid(86400);
+    format!("{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z", s / 3600, s / 60 % 60, s % 60)
+}
+
+// days since 1970-01-01 in the proleptic Gregorian calendar, and back,
+// from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + (m <= 2) as i64, m, d)
+}
+
+impl IntFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            IntFormat::Dec => "dec",
+            IntFormat::Hex => "hex",
+            IntFormat::HhMm => "h:mm",
+            IntFormat::Addr => "addr",
+            IntFormat::Bytes => "bytes",
+            IntFormat::Human => "hum",
+            IntFormat::Time => "time",
+            IntFormat::Duration => "dur",
+            IntFormat::Char => "chr",
+            IntFormat::Bits => "bits",
+            IntFormat::All => "all",
+            IntFormat::Ip => "ip",
+            IntFormat::Rgb => "rgb",
+            IntFormat::Eng => "eng",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum IntFormat {
+    #[default]
+    Dec,
+    Hex,
+    /// Minutes as hours and minutes, `7:45`.
+    HhMm,
+    /// Hex in groups of four digits, `0x7fff_e3a0_1c40`.
+    Addr,
+    /// A size in bytes, and humanized, `7232 (7.1 KiB)`.
+    Bytes,
+    /// Just the humanized size, `7.1 KiB`.
+    Human,
+    /// Unix time as a UTC datetime, `2024-06-01T12:00:00Z`.
+    Time,
+    /// Seconds as days, hours, minutes and seconds, `1d 2h 30m 15s`.
+    Duration,
+    /// Code points as characters, `'a'`.
+    Char,
+    /// Binary as wide as the word size, in groups of four: `0000_0101`.
+    Bits,
+    /// Decimal, and hex, octal and binary too: `10 (0xa 0o12 0b1010)`.
+    All,
+    /// IPv4 addresses, `192.168.1.17`.
+    Ip,
+    /// Colors, `#ff8800`.
+    Rgb,
+    /// Engineering notation, floats too: `1.5e3`, `250e-6`.
+    Eng,
+}
+
+/// Everything an evaluation reads and changes: the stack, variables and the
+/// display settings.
+#[derive(Clone, Default, Debug)]
+pub struct State {
+    pub stack: Vec<Value>,
+    /// Set with `>name`, read with `$name`.
+    pub vars: BTreeMap<String, Value>,
+    /// The stacks of earlier committed lines, `@1` is the first one.
+    pub results: Vec<Vec<Value>>,
+    pub int_format: IntFormat,
+    /// Bits in an integer, results wrap around to fit. 0 means 64.
+    pub word_size: u32,
+    /// Refuse to switch to a word size that some values don't fit in.
+    pub strict: bool,
+    /// Show the error bound of float values.
+    pub show_err: bool,
+    /// Show booleans as 1 and 0 rather than true and false.
+    pub numeric_bools: bool,
+    /// Group the digits of decimals by thousands and of hex by four.
+    pub grouped: bool,
+    /// Divide integers to exact fractions rather than truncating.
+    pub rational: bool,
+    /// Record the stack after every word, see [`Effect::Trace`].
+    pub trace: bool,
+    /// Show how long evaluating the line took, where the frontend times it.
+    pub timed: bool,
+    /// State of the random number generator, seeded on first use if unset.
+    pub rng: Option<u64>,
+    /// Decimals to show floats with, all of them if unset. Only changes the
+    /// display, `round` and `trunc` change the value.
+    pub precision: Option<usize>,
+    /// Show the top of the stack first, on the left, rather than last.
+    pub top_first: bool,
+    /// Reads the input saved by a [`Effect::Save`], for `.load`. Unset where
+    /// there is nowhere to keep sessions.
+    pub loader: Option<Loader>,
+    /// Refuse the words that touch files, for input from untrusted places.
+    pub sandbox: bool,
+    /// The current unix time, for `now`. Unset where there is no clock.
+    pub clock: Option<fn() -> i64>,
+    /// The name of the stack in use, `None` for `main`.
+    pub workspace: Option<String>,
+    /// The other stacks, parked by `.stack <name>`.
+    pub workspaces: BTreeMap<String, Workspace>,
+    /// Named values from the config, pushed by their name like the
+    /// [`CONSTANTS`].
+    pub constants: BTreeMap<String, Value>,
+    /// What the frontend should do once this evaluation is committed.
+    pub effects: Vec<Effect>,
+}
+
+/// A stack parked by `.stack <name>`, with its own variables and format.
+#[derive(Clone, Default, Debug)]
+pub struct Workspace {
+    pub stack: Vec<Value>,
+    pub vars: BTreeMap<String, Value>,
+    pub int_format: IntFormat,
+}
+
+/// Looks up a saved session by name, returning its input.
+pub type Loader = fn(&str) -> Result<String, String>;
+
+/// A side effect of evaluating some input. The evaluation only records it,
+/// as input is evaluated again on every key press while it is typed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Effect {
+    /// `.save <name>`, keep `input` so `.load <name>` can restore it.
+    Save { name: String, input: String },
+    /// `.vars`, show [`State::vars_listing`].
+    ShowVars,
+    /// `.help`, show what the words and syntax do.
+    ShowHelp,
+    /// A word threw away what is described, which an interactive frontend
+    /// may want to confirm.
+    Discard(String),
+    /// A word did something the user should know about, like changing
+    /// values on the stack.
+    Warn(String),
+    /// With `.trace`, a word (and its arguments) and the stack after it.
+    Trace(String, String),
+    /// `.load`, the stack and settings are the session's now, also for a
+    /// frontend that starts each line afresh.
+    Loaded,
+}
+
+impl State {
+    pub fn workspace_name(&self) -> &str {
+        self.workspace.as_deref().unwrap_or("main")
+    }
+
+    /// Bits in an integer, see [`State::word_size`].
+    pub fn bits(&self) -> u32 {
+        if self.word_size == 0 { 64 } else { self.word_size }
+    }
+
+    /// `x` wrapped around to the word size, as a signed integer.
+    pub fn wrap(&self, x: Value) -> Value {
+        match x {
+            Value::Int(x) => {
+                let shift = 64 - self.bits();
+                Value::Int((x << shift) >> shift)
+            },
+            x => x,
+        }
+    }
+
+    /// The bits of `x` that fit the word size, for showing it in hex.
+    fn unsigned(&self, x: i64) -> u64 {
+        x as u64 & (u64::MAX >> (64 - self.bits()))
+    }
+
+    /// splitmix64
+    fn random(&mut self) -> u64 {
+        let s = self.rng.get_or_insert_with(|| RandomState::new().build_hasher().finish());
+        *s = s.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = *s;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, without modulo bias.
+    fn random_below(&mut self, n: u64) -> u64 {
+        // 2^64 mod n, the low products below it would be picked too often
+        let zone = n.wrapping_neg() % n;
+        loop {
+            let m = self.random() as u128 * n as u128;
+            if m as u64 >= zone {
+                return (m >> 64) as u64;
+            }
+        }
+    }
+
+    /// The stack as a line of text, according to the display settings.
+    pub fn render(&self) -> String {
+        let mut out = "".to_owned();
+        for &x in self.shown() {
+            out.push_str(&self.render_value(x));
+            out.push(' ');
+        }
+
+        out
+    }
+
+    /// In `.bits`, the positions of the first bit of every byte, and of bit
+    /// 0, to show under the top of the stack. As wide as the top is shown.
+    pub fn bit_ruler(&self) -> Option<String> {
+        if self.int_format != IntFormat::Bits || !matches!(self.stack.last(), Some(Value::Int(_))) {
+            return None;
+        }
+        let bits = self.bits() as usize;
+        // the column of bit i, counting the separators before it
+        let column = |i: usize| (bits - 1 - i) + (bits - 1 - i) / 4;
+        let mut ruler = vec![' '; column(0) + 1];
+        for i in (7..bits).step_by(8).chain([0]) {
+            for (at, c) in (column(i)..).zip(i.to_string().chars()) {
+                ruler[at] = c;
+            }
+        }
+        Some(ruler.into_iter().collect())
+    }
+
+    /// The stack in the order it is shown in.
+    pub fn shown(&self) -> Box<dyn Iterator<Item = &Value> + '_> {
+        if self.top_first {
+            Box::new(self.stack.iter().rev())
+        } else {
+            Box::new(self.stack.iter())
+        }
+    }
+
+    pub fn render_value(&self, x: Value) -> String {
+        let decimal = |x: String| if self.grouped { group_thousands(&x) } else { x };
+        match x {
+            Value::Int(x) if self.grouped && self.int_format == IntFormat::Hex => addr(self.unsigned(x)),
+            Value::Int(x) if self.int_format == IntFormat::Hex => format!("{:#x}", self.unsigned(x)),
+            Value::Int(x) if self.int_format == IntFormat::HhMm => {
+                let sign = if x < 0 { "-" } else { "" };
+                format!("{sign}{}:{:02}", x.unsigned_abs() / 60, x.unsigned_abs() % 60)
+            },
+            Value::Int(x) if self.int_format == IntFormat::Addr => addr(self.unsigned(x)),
+            Value::Int(x) if self.int_format == IntFormat::Bytes => format!("{} ({})", decimal(x.to_string()), humanize_bytes(x as f64)),
+            Value::Int(x) if self.int_format == IntFormat::Human => humanize_bytes(x as f64),
+            Value::Int(x) if self.int_format == IntFormat::Time => datetime(x),
+            Value::Int(x) if self.int_format == IntFormat::Duration => duration(x),
+            Value::Int(x) if self.int_format == IntFormat::Ip && u32::try_from(x).is_ok() => ipv4(x as u32),
+            Value::Int(x) if self.int_format == IntFormat::Rgb && (0..1 << 24).contains(&x) => format!("#{x:06x}"),
+            Value::Int(x) if self.int_format == IntFormat::All => {
+                let u = self.unsigned(x);
+                format!("{} ({u:#x} {u:#o} {u:#b})", decimal(x.to_string()))
+            },
+            Value::Int(x) if self.int_format == IntFormat::Bits => in_binary(self.unsigned(x), self.bits()),
+            Value::Int(x) if self.int_format == IntFormat::Char => character(x).unwrap_or_else(|| decimal(x.to_string())),
+            Value::Int(x) if self.int_format == IntFormat::Eng => engineering(format!("{x:e}")),
+            Value::Int(x) => decimal(x.to_string()),
+            Value::Float(x, err) if self.show_err && err != 0.0 && x.is_finite() => {
+                format!("{}±{:.1}ulp", decimal(self.render_float(x)), err / ulp(x))
+            },
+            Value::Float(x, _) if x.is_finite() && self.int_format == IntFormat::Human => humanize_bytes(x),
+            Value::Float(x, _) if x.is_finite() && self.int_format == IntFormat::Eng => self.render_engineering(x),
+            Value::Float(x, _) if x.is_finite() => decimal(self.render_float(x)),
+            Value::Float(x, _) => self.render_float(x),
+            Value::Bool(b) if self.numeric_bools => (b as i64).to_string(),
+            Value::Bool(b) => b.to_string(),
+            // 500 MB rather than 500.0 MB
+            Value::Quantity(x, unit) if self.precision.is_none() && x.is_finite() => format!("{} {}", decimal(x.to_string()), unit.name),
+            Value::Quantity(x, unit) => format!("{} {}", decimal(self.render_float(x)), unit.name),
+            Value::Cidr(addr, prefix) => format!("{}/{prefix}", ipv4(addr)),
+            Value::Ratio(n, d) => format!("{}/{}", decimal(n.to_string()), decimal(d.to_string())),
+        }
+    }
+
+    fn render_engineering(&self, x: f64) -> String {
+        let eng = engineering(format!("{x:e}"));
+        let Some(p) = self.precision else {
+            return eng;
+        };
+        let mut exp = eng.split_once('e').unwrap().1.parse::<i32>().unwrap();
+        let mut mantissa = format!("{:.p$}", x / 10f64.powi(exp));
+        // rounded up to the next power of a thousand, 999.99 to 1000.0
+        if mantissa.trim_start_matches('-').split('.').next().unwrap().len() > 3 {
+            exp += 3;
+            mantissa = format!("{:.p$}", x / 10f64.powi(exp));
+        }
+        format!("{mantissa}e{exp}")
+    }
+
+    fn render_float(&self, x: f64) -> String {
+        match self.precision {
+            Some(p) => format!("{x:.p$}"),
+            None => format!("{x:?}"),
+        }
+    }
+
+    /// The variables and their values, a line each.
+    pub fn vars_listing(&self) -> Vec<String> {
+        if self.vars.is_empty() {
+            return vec!["no variables, set one with >name".into()];
+        }
+        let width = self.vars.keys().map(|name| name.len()).max().unwrap_or(0);
+        self.vars.iter().map(|(name, &x)| format!("${name:width$}  {}", self.render_value(x))).collect()
+    }
+
+    /// Input that evaluates back to this stack, variables and display
+    /// settings (but not the error bounds of floats), starting from the
+    /// default settings.
+    pub fn to_input(&self) -> String {
+        self.to_input_from(&State::default())
+    }
+
+    /// Like [`State::to_input`], for evaluating on top of the settings of
+    /// `base`. Only the settings that differ are written, as the words for
+    /// most of them toggle rather than set.
+    pub fn to_input_from(&self, base: &State) -> String {
+        let float = |x: f64| match x {
+            _ if x.is_nan() => "0.0 0 d".into(),
+            _ if x.is_infinite() => format!("{:.1} 0 d", x.signum()),
+            // Display never uses an exponent, so this always parses
+            _ if x.fract() == 0.0 => format!("{x}.0"),
+            _ => x.to_string(),
+        };
+        let literal = |x| match x {
+            Value::Int(x) => x.to_string(),
+            Value::Float(x, _) => float(x),
+            Value::Bool(b) => b.to_string(),
+            Value::Quantity(x, unit) => format!("{} {}", float(x), unit.name),
+            Value::Cidr(addr, prefix) => format!("{}/{prefix}", ipv4(addr)),
+            Value::Ratio(n, d) => format!("{n}/{d}"),
+        };
+
+        let mut out = vec![];
+        let workspace = |out: &mut Vec<String>, stack: &[Value], vars: &BTreeMap<String, Value>, int_format, from| {
+            for (name, &x) in vars.iter() {
+                out.push(format!("{} >{name}", literal(x)));
+            }
+            for &x in stack.iter() {
+                out.push(literal(x));
+            }
+            if int_format == from {
+                return;
+            }
+            match int_format {
+                IntFormat::Dec => out.push(".d".into()),
+                IntFormat::Hex => out.push(".h".into()),
+                IntFormat::HhMm => out.push(".hhmm".into()),
+                IntFormat::Addr => out.push(".addr".into()),
+                IntFormat::Bytes => out.push(".bytes".into()),
+                IntFormat::Human => out.push(".hum".into()),
+                IntFormat::Time => out.push(".t".into()),
+                IntFormat::Duration => out.push(".dur".into()),
+                IntFormat::Char => out.push("chr".into()),
+                IntFormat::Bits => out.push(".bits".into()),
+                IntFormat::All => out.push(".a".into()),
+                IntFormat::Ip => out.push(".ip".into()),
+                IntFormat::Rgb => out.push(".rgb".into()),
+                IntFormat::Eng => out.push(".e".into()),
+            }
+        };
+        // the parked stacks, and back to the one in use
+        for (name, parked) in self.workspaces.iter() {
+            out.push(format!(".stack {name}"));
+            workspace(&mut out, &parked.stack, &parked.vars, parked.int_format, IntFormat::Dec);
+        }
+        if !self.workspaces.is_empty() {
+            out.push(format!(".stack {}", self.workspace_name()));
+        }
+        workspace(&mut out, &self.stack, &self.vars, self.int_format, base.int_format);
+        // before .strict, which would refuse it
+        if self.bits() != base.bits() {
+            out.push(format!(".w{}", self.bits()));
+        }
+        let toggles = [
+            (self.show_err, base.show_err, ".err"),
+            (self.numeric_bools, base.numeric_bools, ".bool"),
+            (self.grouped, base.grouped, ".g"),
+            (self.rational, base.rational, ".q"),
+            (self.strict, base.strict, ".strict"),
+            (self.trace, base.trace, ".trace"),
+            (self.timed, base.timed, ".time"),
+        ];
+        out.extend(toggles.iter().filter(|(on, was, _)| on != was).map(|(_, _, word)| word.to_string()));
+        if self.precision != base.precision {
+            out.push(format!(".prec {}", self.precision.map_or("off".into(), |p| p.to_string())));
+        }
+        if self.top_first != base.top_first {
+            out.push(format!(".top {}", if self.top_first { "first" } else { "last" }));
+        }
+
+        out.join(" ")
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EvalError {
+    msg: String,
+    span: Option<std::ops::Range<usize>>,
+}
+
+impl EvalError {
+    fn new(msg: impl Into<String>) -> EvalError {
+        EvalError { msg: msg.into(), span: None }
+    }
+
+    /// Where in the input the word that failed is, in bytes, its
+    /// arguments included.
+    pub fn span(&self) -> Option<std::ops::Range<usize>> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Parse a number literal: a decimal or `0x` hex integer, `h:mm` minutes
+/// or a decimal float, any of the decimals with an SI or IEC suffix like
+/// `4k` or `64Ki`, an ISO 8601 datetime as unix time, a duration like
+/// `2h30m` as seconds, a character like `'a'` or `U+1F600` as its code
+/// point, an IPv4 address, a subnet like `10.0.0.0/8`, or a color like
+/// `#ff8800`.
+///
+/// Hex takes the full 64 bits, so addresses like `0xffff_8000_0000_0000`
+/// come out negative, and may be grouped with `_` as [`IntFormat::Addr`]
+/// shows them. Decimals may be grouped too, like `1_000_000`.
+pub fn parse_number(x: &str) -> Option<Value> {
+    if x.contains('_') && !x.starts_with("0x") {
+        let digits = x.strip_prefix('-').unwrap_or(x);
+        let grouped = digits.split('_').all(|group| !group.is_empty()) &&
+            digits.bytes().all(|c| c.is_ascii_digit() || c == b'.' || c == b'_');
+        return grouped.then(|| parse_number(&x.replace('_', ""))).flatten();
+    }
+
+    if let Some(x) = x.strip_prefix("0x") &&
+        x.split('_').all(|group| !group.is_empty()) &&
+        let Ok(num) = u64::from_str_radix(&x.replace('_', ""), 16) {
+        return Some(Value::Int(num as i64));
+    }
+
+    if let Ok(num) = x.parse() {
+        return Some(Value::Int(num));
+    }
+
+    if let Some(time) = parse_datetime(x) {
+        return Some(Value::Int(time));
+    }
+
+    if let Some(minutes) = parse_hhmm(x) {
+        return Some(Value::Int(minutes));
+    }
+
+    if let Some(seconds) = parse_duration(x) {
+        return Some(Value::Int(seconds));
+    }
+
+    if let Some(c) = parse_char(x) {
+        return Some(Value::Int(c));
+    }
+
+    if let Some(addr) = parse_ipv4(x) {
+        return Some(Value::Int(addr as i64));
+    }
+
+    if let Some(color) = parse_rgb(x) {
+        return Some(Value::Int(color));
+    }
+
+    if let Some(subnet) = parse_cidr(x) {
+        return Some(subnet);
+    }
+
+    if let Some((n, d)) = x.split_once('/') &&
+        let (Ok(n), Ok(d)) = (n.parse::<i64>(), d.parse::<i64>()) {
+        return ratio(n as i128, d as i128).ok();
+    }
+
+    if let Some(num) = parse_suffixed(x) {
+        return Some(num);
+    }
+
+    parse_float(x).map(|(num, e)| Value::Float(num, e))
+}
+
+/// Evaluate `inp` on top of `state`, returning the resulting stack.
+///
+/// Evaluation stops at the first word that fails, leaving `state` as it
+/// was just before that word.
+pub fn eval(inp: &str, state: &mut State) -> Result<Vec<Value>, EvalError> {
+    eval_from(inp, 0, state, |_, _| {})
+}
+
+/// [`eval`] the words of `inp` from byte `from` on, `state` being what
+/// the words before left. `step` gets where each word that succeeded ends,
+/// its arguments included, and the state after it: a `from` to pick up
+/// from once the input after that changes.
+pub fn eval_from(inp: &str, from: usize, state: &mut State, mut step: impl FnMut(usize, &State)) -> Result<Vec<Value>, EvalError> {
+    let infix = infix_expr(inp);
+    if let Some((offset, expr)) = infix {
+        // before evaluating any of the line
+        infix_words(expr, offset)?;
+    }
+    let rpn = infix.map_or(inp, |(offset, _)| &inp[..offset - 1]);
+
+    let mut words = words(rpn).skip_while(|(span, _)| span.start < from);
+    while let Some((span, x)) = words.next() {
+        // the end of the last argument the word took
+        let mut end = span.end;
+        let mut args = words.by_ref().map(|(span, arg)| {
+            end = span.end;
+            arg
+        });
+        let res = word(x, &mut args, state);
+        if let Err(e) = res {
+            return Err(EvalError { span: Some(span.start..end), ..e });
+        }
+        if state.trace {
+            let stack = state.render().trim_end().to_owned();
+            state.effects.push(Effect::Trace(inp[span.start..end].to_owned(), stack));
+        }
+        step(end, state);
+    }
+
+    if let Some((offset, expr)) = infix {
+        return eval_infix(infix_words(expr, offset)?, state);
+    }
+    Ok(state.stack.clone())
+}
+
+/// The whitespace separated words of `inp` and where they are.
+fn words(inp: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut start = None;
+    // whitespace between quotes, like in x"dead beef", doesn't split
+    let mut quoted = false;
+    inp.char_indices().chain([(inp.len(), ' ')]).filter_map(move |(i, c)| {
+        quoted ^= c == '"';
+        let split = c.is_whitespace() && (!quoted || i == inp.len());
+        match (split, start) {
+            (true, Some(from)) => {
+                start = None;
+                Some((from..i, &inp[from..i]))
+            },
+            (false, None) => {
+                start = Some(i);
+                None
+            },
+            _ => None,
+        }
+    })
+}
+
+/// From a word starting with `=`, like `=(1920*1080*4)/1048576`, or a `=`
+/// starting the line, the rest of the line is an infix expression: the
+/// expression, and where it starts.
+fn infix_expr(inp: &str) -> Option<(usize, &str)> {
+    let (_, (span, _)) = words(inp).enumerate()
+        .find(|(i, (_, x))| (x.len() > 1 && x.starts_with('=')) || (*i == 0 && *x == "="))?;
+    Some((span.start + 1, &inp[span.start + 1..]))
+}
+
+const INFIX_OPS: &str = "()+-−*×/÷%,²";
+
+/// The operators, parentheses and operands of an infix expression, and
+/// where they are in the line it starts at `offset` of.
+fn infix_pieces(expr: &str, offset: usize) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut out = vec![];
+    let mut chars = expr.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        if !INFIX_OPS.contains(c) {
+            while let Some(&(j, c)) = chars.peek() {
+                let operand = &expr[i..j];
+                // the sign of an exponent, 2.5e-3
+                let exponent = matches!(c, '+' | '-') && operand.ends_with(['e', 'E']) &&
+                    operand.starts_with(|c: char| c.is_ascii_digit()) && !operand.starts_with("0x");
+                if c.is_whitespace() || (INFIX_OPS.contains(c) && !exponent) {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+        }
+        out.push((offset + i..offset + end, &expr[i..end]));
+    }
+    out
+}
+
+/// An infix expression as the words that evaluate it, in RPN, and the
+/// pieces of the expression they're from.
+fn infix_words(expr: &str, offset: usize) -> Result<Vec<(std::ops::Range<usize>, &str)>, EvalError> {
+    let mut parser = Infix { pieces: infix_pieces(expr, offset), next: 0, end: offset + expr.len(), out: vec![] };
+    if parser.pieces.is_empty() {
+        return Ok(vec![]);
+    }
+    parser.sum()?;
+    if let Some((span, x)) = parser.pieces.get(parser.next).cloned() {
+        return Err(parser.error(span, format!("expected an operator, not '{x}'")));
+    }
+    Ok(parser.out)
+}
+
+/// Precedence climbing over [`infix_pieces`]: `-` and `+` bind the
+/// loosest, then `*`, `/` and `%`, then a unary minus, then `²`. Operators become
+/// the words that do the same, but `/`, which isn't a word.
+struct Infix<'a> {
+    pieces: Vec<(std::ops::Range<usize>, &'a str)>,
+    next: usize,
+    /// The end of the line, for errors about what's missing there.
+    end: usize,
+    out: Vec<(std::ops::Range<usize>, &'a str)>,
+}
+
+impl<'a> Infix<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.pieces.get(self.next).map(|piece| piece.1)
+    }
+
+    fn advance(&mut self) -> Option<(std::ops::Range<usize>, &'a str)> {
+        let piece = self.pieces.get(self.next).cloned();
+        self.next += piece.is_some() as usize;
+        piece
+    }
+
+    fn error(&self, span: std::ops::Range<usize>, msg: String) -> EvalError {
+        EvalError { span: Some(span), ..EvalError::new(msg) }
+    }
+
+    fn expect(&mut self, what: &str) -> Result<(), EvalError> {
+        match self.advance() {
+            Some((_, x)) if x == what => Ok(()),
+            Some((span, x)) => Err(self.error(span, format!("expected '{what}', not '{x}'"))),
+            None => Err(self.error(self.end..self.end, format!("expected '{what}'"))),
+        }
+    }
+
+    fn sum(&mut self) -> Result<(), EvalError> {
+        self.product()?;
+        while let Some(op @ ("+" | "-" | "−")) = self.peek() {
+            let (span, _) = self.advance().unwrap();
+            self.product()?;
+            self.out.push((span, if op == "+" { "+" } else { "-" }));
+        }
+        Ok(())
+    }
+
+    fn product(&mut self) -> Result<(), EvalError> {
+        self.unary()?;
+        while let Some(op @ ("*" | "×" | "/" | "÷" | "%")) = self.peek() {
+            let (span, _) = self.advance().unwrap();
+            self.unary()?;
+            self.out.push((span, match op {
+                "×" => "*",
+                "÷" => "/",
+                op => op,
+            }));
+        }
+        Ok(())
+    }
+
+    fn unary(&mut self) -> Result<(), EvalError> {
+        match self.peek() {
+            Some("-" | "−") => {
+                let (span, _) = self.advance().unwrap();
+                self.unary()?;
+                self.out.push((span, "neg"));
+                Ok(())
+            },
+            Some("+") => {
+                self.advance();
+                self.unary()
+            },
+            _ => {
+                self.operand()?;
+                while self.peek() == Some("²") {
+                    let (span, _) = self.advance().unwrap();
+                    self.out.push((span, "sq"));
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// A parenthesized expression, a call like `gcd(12, 18)`, or a number,
+    /// variable or other word, with the units that follow it.
+    fn operand(&mut self) -> Result<(), EvalError> {
+        let Some((span, x)) = self.advance() else {
+            return Err(self.error(self.end..self.end, "the expression ends too early".into()));
+        };
+        if x == "(" {
+            self.sum()?;
+            return self.expect(")");
+        }
+        if INFIX_OPS.contains(x) {
+            return Err(self.error(span, format!("expected a number, not '{x}'")));
+        }
+        if self.peek() == Some("(") {
+            self.advance();
+            if self.peek() != Some(")") {
+                self.sum()?;
+                while self.peek() == Some(",") {
+                    self.advance();
+                    self.sum()?;
+                }
+            }
+            self.expect(")")?;
+        }
+        self.out.push((span, x));
+        while let Some(unit) = self.peek().filter(|&unit| find_unit(unit).is_some()) {
+            let (span, _) = self.advance().unwrap();
+            self.out.push((span, unit));
+        }
+        Ok(())
+    }
+}
+
+// =<expr>
+fn eval_infix(rpn: Vec<(std::ops::Range<usize>, &str)>, state: &mut State) -> Result<Vec<Value>, EvalError> {
+    for (span, x) in rpn {
+        let res = match x {
+            "/" => divide(state),
+            _ => word(x, &mut std::iter::empty(), state),
+        };
+        if let Err(e) = res {
+            return Err(EvalError { span: Some(span), ..e });
+        }
+        if state.trace {
+            let stack = state.render().trim_end().to_owned();
+            state.effects.push(Effect::Trace(x.to_owned(), stack));
+        }
+    }
+    Ok(state.stack.clone())
+}
+
+// the / of infix, ( a b --- a/b )
+fn divide(state: &mut State) -> Result<(), EvalError> {
+    // the usual division, 7/2 is 3.5 rather than the 3 of d, or 7/2 in .q
+    let n = state.stack.len();
+    if let [.., Value::Int(a), Value::Int(b)] = state.stack[..] && b != 0 && a.wrapping_rem(b) != 0 && !state.rational {
+        let (a, err) = Value::Int(a).to_float();
+        state.stack[n - 2] = Value::Float(a, err);
+    }
+    binary(state, Op::Div)
+}
+
+/// A word of the language, see [`WORDS`].
+pub struct Word {
+    /// The spellings of the word, the first is the main one.
+    pub names: &'static [&'static str],
+    /// Stack effect and a short description.
+    pub help: &'static str,
+    run: Run,
+}
+
+enum Run {
+    Plain(fn(&mut State) -> Result<(), EvalError>),
+    /// Takes the next words of the input as its arguments, like `.prec 2`,
+    /// with names for them to show in help.
+    Arg(&'static [&'static str], fn(&mut State, &[&str]) -> Result<(), EvalError>),
+}
+
+impl Word {
+    /// What the arguments a word takes from the input are called.
+    pub fn args(&self) -> &'static [&'static str] {
+        match self.run {
+            Run::Plain(_) => &[],
+            Run::Arg(args, _) => args,
+        }
+    }
+}
+
+/// Every word the evaluator looks up by name. Number literals, `/op` folds
+/// and variables are syntax rather than words, see [`SYNTAX`].
+pub static WORDS: &[Word] = &[
+    Word { names: &["+", "p"], help: "( a b -- a+b ) add", run: Run::Plain(|s| binary(s, Op::Add)) },
+    Word { names: &["-", "−"], help: "( a b -- a-b ) subtract", run: Run::Plain(|s| binary(s, Op::Sub)) },
+    Word { names: &["*", "m", "×"], help: "( a b -- a*b ) multiply", run: Run::Plain(|s| binary(s, Op::Mul)) },
+    Word { names: &["d", "÷"], help: "( a b -- a/b ) divide", run: Run::Plain(|s| binary(s, Op::Div)) },
+    Word { names: &["sq", "²"], help: "( x -- x*x ) square, also after a number or variable: 3²", run: Run::Plain(square) },
+    Word { names: &["%"], help: "( a b -- a%b ) remainder", run: Run::Plain(|s| binary(s, Op::Rem)) },
+    Word { names: &["%of"], help: "( a b -- a*b/100 ) b percent of a", run: Run::Plain(|s| percent(s, "%of")) },
+    Word { names: &["pct+"], help: "( a b -- a' ) a increased by b percent", run: Run::Plain(|s| percent(s, "pct+")) },
+    Word { names: &["pct-"], help: "( a b -- a' ) a decreased by b percent", run: Run::Plain(|s| percent(s, "pct-")) },
+    Word { names: &["pctchg"], help: "( a b -- p ) the change from a to b, in percent", run: Run::Plain(|s| percent(s, "pctchg")) },
+    Word { names: &["gcd"], help: "( a b -- gcd ) greatest common divisor", run: Run::Plain(|s| gcd_lcm(s, "gcd")) },
+    Word { names: &["lcm"], help: "( a b -- lcm ) least common multiple", run: Run::Plain(|s| gcd_lcm(s, "lcm")) },
+    Word { names: &["isprime"], help: "( n -- b ) whether n is prime", run: Run::Plain(|s| prime(s, "isprime")) },
+    Word { names: &["nextprime"], help: "( n -- p ) the smallest prime > n", run: Run::Plain(|s| prime(s, "nextprime")) },
+    Word { names: &["factor"], help: "( n -- p q .. ) the prime factors, smallest first", run: Run::Plain(factor) },
+    Word { names: &["powmod"], help: "( a b m -- a^b%m ) modular exponentiation", run: Run::Plain(powmod) },
+    Word { names: &["invmod"], help: "( a m -- x ) the x with a*x%m = 1", run: Run::Plain(invmod) },
+    Word { names: &["!"], help: "( n -- n! ) factorial", run: Run::Plain(factorial) },
+    Word { names: &["ncr"], help: "( n k -- c ) the ways to choose k of n things", run: Run::Plain(|s| choose(s, "ncr")) },
+    Word { names: &["npr"], help: "( n k -- p ) the ways to arrange k of n things", run: Run::Plain(|s| choose(s, "npr")) },
+    Word { names: &["abs"], help: "( x -- |x| ) absolute value", run: Run::Plain(|s| unary(s, "abs")) },
+    Word { names: &["sign"], help: "( x -- s ) -1, 0 or 1 for negative, zero or positive", run: Run::Plain(|s| unary(s, "sign")) },
+    Word { names: &["neg"], help: "( x -- -x ) negate", run: Run::Plain(|s| unary(s, "neg")) },
+    Word { names: &["i"], help: "( n -- 1 .. n ) iota", run: Run::Plain(iota) },
+    Word { names: &["rand"], help: "( n -- x ) random 0 <= x < n", run: Run::Plain(rand) },
+    Word { names: &["true"], help: "( -- true ) 1, shown as true", run: Run::Plain(|s| { s.stack.push(Value::Bool(true)); Ok(()) }) },
+    Word { names: &["false"], help: "( -- false ) 0, shown as false", run: Run::Plain(|s| { s.stack.push(Value::Bool(false)); Ok(()) }) },
+    Word { names: &["="], help: "( x y -- b ) whether x equals y", run: Run::Plain(|s| compare(s, "=", Ordering::is_eq)) },
+    Word { names: &["<"], help: "( x y -- b ) whether x < y", run: Run::Plain(|s| compare(s, "<", Ordering::is_lt)) },
+    Word { names: &[">"], help: "( x y -- b ) whether x > y", run: Run::Plain(|s| compare(s, ">", Ordering::is_gt)) },
+    Word { names: &["<="], help: "( x y -- b ) whether x <= y", run: Run::Plain(|s| compare(s, "<=", Ordering::is_le)) },
+    Word { names: &[">="], help: "( x y -- b ) whether x >= y", run: Run::Plain(|s| compare(s, ">=", Ordering::is_ge)) },
+    Word { names: &["sel"], help: "( c a b -- x ) a if c isn't 0, else b", run: Run::Plain(select) },
+    Word { names: &["rev"], help: "( a b .. -- .. b a ) reverse the stack", run: Run::Plain(|s| { s.stack.reverse(); Ok(()) }) },
+    Word { names: &["sort"], help: "( .. -- .. ) sort the stack, smallest first", run: Run::Plain(|s| sort(s, "sort")) },
+    Word { names: &["rsort"], help: "( .. -- .. ) sort the stack, largest first", run: Run::Plain(|s| sort(s, "rsort")) },
+    Word { names: &["uniq"], help: "( .. -- .. ) drop values equal to one further down the stack", run: Run::Plain(|s| { uniq(s); Ok(()) }) },
+    Word { names: &["clear"], help: "( .. -- ) empty the stack", run: Run::Plain(clear) },
+    Word { names: &["to"], help: "( x -- x' ) convert a quantity to another unit of the same kind, 500 MB to MiB", run: Run::Arg(&["unit"], |s, a| convert(s, a[0])) },
+    Word { names: &["chr"], help: "show integers as the characters they are the code points of", run: Run::Plain(|s| { s.int_format = IntFormat::Char; Ok(()) }) },
+    Word { names: &["ord"], help: "show characters as their code points again", run: Run::Plain(|s| { s.int_format = IntFormat::Dec; Ok(()) }) },
+    Word { names: &["bswap16"], help: "( x -- x' ) reverse the bytes of the low 16 bits", run: Run::Plain(|s| byte_swap(s, 16)) },
+    Word { names: &["bswap32"], help: "( x -- x' ) reverse the bytes of the low 32 bits", run: Run::Plain(|s| byte_swap(s, 32)) },
+    Word { names: &["bswap64"], help: "( x -- x' ) reverse the bytes", run: Run::Plain(|s| byte_swap(s, 64)) },
+    Word { names: &["popcount"], help: "( x -- n ) the number of bits set", run: Run::Plain(|s| count_bits(s, "popcount", |x, _| x.count_ones())) },
+    Word { names: &["clz"], help: "( x -- n ) the number of leading zero bits, in the word size", run: Run::Plain(|s| count_bits(s, "clz", |x, bits| x.leading_zeros() - (64 - bits))) },
+    Word { names: &["ctz"], help: "( x -- n ) the number of trailing zero bits, in the word size", run: Run::Plain(|s| count_bits(s, "ctz", |x, bits| x.trailing_zeros().min(bits))) },
+    Word { names: &["parity"], help: "( x -- p ) 1 if an odd number of bits is set, otherwise 0", run: Run::Plain(|s| count_bits(s, "parity", |x, _| x.count_ones() % 2)) },
+    Word { names: &["bits"], help: "( x hi lo -- field ) bits hi down to lo of x", run: Run::Plain(extract_bits) },
+    Word { names: &["setbits"], help: "( x hi lo v -- x' ) x with bits hi down to lo set to v", run: Run::Plain(set_bits) },
+    Word { names: &["mask"], help: "( n -- mask ) the low n bits set", run: Run::Plain(mask) },
+    Word { names: &["f2b"], help: "( x -- bits ) the bits of a float, in hex", run: Run::Plain(float_to_bits) },
+    Word { names: &["b2f"], help: "( bits -- x ) the float with these bits", run: Run::Plain(bits_to_float) },
+    Word { names: &["ieee"], help: "( x -- sign exponent mantissa ) the fields of a float, the exponent still biased", run: Run::Plain(ieee) },
+    Word { names: &["now"], help: "( -- t ) the current unix time", run: Run::Plain(now) },
+    Word { names: &["off"], help: "( a b -- b-a ) the offset between two addresses, in bytes", run: Run::Plain(offset) },
+    Word { names: &["is_p2"], help: "( x -- b ) whether x is a power of two", run: Run::Plain(|s| power_of_two(s, "is_p2", |x| Some(Value::Bool(x.count_ones() == 1)))) },
+    Word { names: &["next_p2"], help: "( x -- p ) the smallest power of two >= x", run: Run::Plain(|s| power_of_two(s, "next_p2", |x| (x as u64).checked_next_power_of_two().and_then(|p| i64::try_from(p).ok()).map(Value::Int))) },
+    Word { names: &["prev_p2"], help: "( x -- p ) the largest power of two <= x", run: Run::Plain(|s| power_of_two(s, "prev_p2", |x| Some(Value::Int(1 << x.ilog2())))) },
+    Word { names: &["log2"], help: "( x -- n ) the integer log2, rounded down", run: Run::Plain(|s| power_of_two(s, "log2", |x| Some(Value::Int(x.ilog2() as i64)))) },
+    Word { names: &["log10"], help: "( x -- n ) the integer log10, rounded down", run: Run::Plain(|s| power_of_two(s, "log10", |x| Some(Value::Int(x.ilog10() as i64)))) },
+    Word { names: &["rgb"], help: "( r g b -- color ) pack 0-255 components into a color", run: Run::Plain(rgb) },
+    Word { names: &["unrgb"], help: "( color -- r g b ) split a color into its components", run: Run::Plain(unrgb) },
+    Word { names: &["crc32"], help: "( bytes.. -- crc ) the CRC-32 of the stack as bytes, with words of 32 bits or more", run: Run::Plain(|s| hash(s, "crc32")) },
+    Word { names: &["fnv1a"], help: "( bytes.. -- hash ) the 64 bit FNV-1a hash of the stack as bytes, with 64 bit words", run: Run::Plain(|s| hash(s, "fnv1a")) },
+    Word { names: &["sha256"], help: "( bytes.. -- digest.. ) the SHA-256 digest of the stack as bytes, as 32 bytes", run: Run::Plain(|s| hash(s, "sha256")) },
+    Word { names: &["net"], help: "( subnet -- addr ) the network address of a subnet", run: Run::Plain(|s| subnet(s, "net")) },
+    Word { names: &["bcast"], help: "( subnet -- addr ) the broadcast address of a subnet", run: Run::Plain(|s| subnet(s, "bcast")) },
+    Word { names: &["hosts"], help: "( subnet -- n ) how many hosts a subnet has addresses for", run: Run::Plain(|s| subnet(s, "hosts")) },
+    Word { names: &["insub"], help: "( addr subnet -- b ) whether an address is in a subnet", run: Run::Plain(in_subnet) },
+    Word { names: &["alup"], help: "( x a -- x' ) round up to a multiple of the power of two a", run: Run::Plain(|s| align(s, "alup")) },
+    Word { names: &["aldn"], help: "( x a -- x' ) round down to a multiple of the power of two a", run: Run::Plain(|s| align(s, "aldn")) },
+    Word { names: &["aloff"], help: "( x a -- x%a ) the offset past a multiple of the power of two a, like in a page", run: Run::Plain(|s| align(s, "aloff")) },
+    Word { names: &["sumtime"], help: "( a b .. -- a+b+.. ) add up the stack and show it as h:mm", run: Run::Plain(sumtime) },
+    Word { names: &["round"], help: "( x n -- x' ) round to n decimals, a negative n rounds to tens, hundreds ..", run: Run::Plain(|s| round(s, false)) },
+    Word { names: &["trunc"], help: "( x n -- x' ) truncate to n decimals", run: Run::Plain(|s| round(s, true)) },
+    Word { names: &[".h"], help: "show integers in hex", run: Run::Plain(|s| { s.int_format = IntFormat::Hex; Ok(()) }) },
+    Word { names: &[".d"], help: "show integers in decimal", run: Run::Plain(|s| { s.int_format = IntFormat::Dec; Ok(()) }) },
+    Word { names: &[".addr"], help: "show integers as addresses, hex in groups of four", run: Run::Plain(|s| { s.int_format = IntFormat::Addr; Ok(()) }) },
+    Word { names: &[".bytes"], help: "show integers as sizes in bytes", run: Run::Plain(|s| { s.int_format = IntFormat::Bytes; Ok(()) }) },
+    Word { names: &[".t"], help: "show integers as UTC datetimes, unix time underneath", run: Run::Plain(|s| { s.int_format = IntFormat::Time; Ok(()) }) },
+    Word { names: &[".dur"], help: "show integers as durations in seconds, 1d 2h 30m 15s", run: Run::Plain(|s| { s.int_format = IntFormat::Duration; Ok(()) }) },
+    Word { names: &[".ip"], help: "show integers as IPv4 addresses", run: Run::Plain(|s| { s.int_format = IntFormat::Ip; Ok(()) }) },
+    Word { names: &[".rgb"], help: "show integers as colors, #rrggbb", run: Run::Plain(|s| { s.int_format = IntFormat::Rgb; Ok(()) }) },
+    Word { names: &[".e"], help: "show integers and floats in engineering notation, 15e3", run: Run::Plain(|s| { s.int_format = IntFormat::Eng; Ok(()) }) },
+    Word { names: &[".a"], help: "show integers in decimal, hex, octal and binary at once", run: Run::Plain(|s| { s.int_format = IntFormat::All; Ok(()) }) },
+    Word { names: &[".bits"], help: "show integers in binary, with the bit positions of the top", run: Run::Plain(|s| { s.int_format = IntFormat::Bits; Ok(()) }) },
+    Word { names: &[".hum"], help: "show sizes human readable, 3.2 GiB", run: Run::Plain(|s| { s.int_format = IntFormat::Human; Ok(()) }) },
+    Word { names: &[".w8"], help: "use 8 bit integers", run: Run::Plain(|s| word_size(s, 8)) },
+    Word { names: &[".w16"], help: "use 16 bit integers", run: Run::Plain(|s| word_size(s, 16)) },
+    Word { names: &[".w32"], help: "use 32 bit integers", run: Run::Plain(|s| word_size(s, 32)) },
+    Word { names: &[".w64"], help: "use 64 bit integers, the default", run: Run::Plain(|s| word_size(s, 64)) },
+    Word { names: &[".strict"], help: "toggle refusing a word size that values on the stack don't fit", run: Run::Plain(|s| { s.strict = !s.strict; Ok(()) }) },
+    Word { names: &[".hhmm"], help: "show integers as minutes in hours and minutes", run: Run::Plain(|s| { s.int_format = IntFormat::HhMm; Ok(()) }) },
+    Word { names: &[".bool"], help: "toggle showing booleans as true and false, or 1 and 0", run: Run::Plain(|s| { s.numeric_bools = !s.numeric_bools; Ok(()) }) },
+    Word { names: &[".g"], help: "toggle grouping digits, decimals by thousands and hex by four", run: Run::Plain(|s| { s.grouped = !s.grouped; Ok(()) }) },
+    Word { names: &[".q"], help: "toggle dividing integers to exact fractions, 1 3 d is 1/3", run: Run::Plain(|s| { s.rational = !s.rational; Ok(()) }) },
+    Word { names: &[".err"], help: "toggle showing the error bound of floats", run: Run::Plain(|s| { s.show_err = !s.show_err; Ok(()) }) },
+    Word { names: &[".prec"], help: "show floats with n decimals, or all of them with `.prec off`", run: Run::Arg(&["n"], |s, a| precision(s, a[0])) },
+    Word { names: &[".seed"], help: "seed the random numbers, so rand gives the same ones again", run: Run::Arg(&["n"], |s, a| seed(s, a[0])) },
+    Word { names: &[".top"], help: "show the top of the stack first or last", run: Run::Arg(&["first|last"], |s, a| top(s, a[0])) },
+    Word { names: &[".trace"], help: "toggle showing the stack after every word, when the line is committed", run: Run::Plain(|s| { s.trace = !s.trace; Ok(()) }) },
+    Word { names: &[".time"], help: "toggle showing how long the line took to evaluate", run: Run::Plain(|s| { s.timed = !s.timed; Ok(()) }) },
+    Word { names: &[".stack"], help: "park the stack, its variables and format, and switch to another one", run: Run::Arg(&["name"], |s, a| switch_stack(s, a[0])) },
+    Word { names: &[".vars"], help: "list the variables", run: Run::Plain(|s| { s.effects.push(Effect::ShowVars); Ok(()) }) },
+    Word { names: &[".help"], help: "list every word, also on F1", run: Run::Plain(|s| { s.effects.push(Effect::ShowHelp); Ok(()) }) },
+    Word { names: &[".save"], help: "save the stack, variables and settings as a session", run: Run::Arg(&["name"], |s, a| save(s, a[0])) },
+    Word { names: &[".load"], help: "replace everything with a saved session", run: Run::Arg(&["name"], |s, a| load(s, a[0])) },
+    Word { names: &[".snip"], help: "save the other words of the line as a snippet, or insert one", run: Run::Arg(&["save|insert", "name"], snip) },
+];
+
+/// Names that push a value: the name, the value and a description. More
+/// can come from the config, see [`State::constants`].
+pub static CONSTANTS: &[(&str, Value, &str)] = &[
+    ("pi", Value::Float(std::f64::consts::PI, 0.0), "π, the ratio of a circle's circumference to its diameter"),
+    ("tau", Value::Float(std::f64::consts::TAU, 0.0), "τ, 2π"),
+    ("e", Value::Float(std::f64::consts::E, 0.0), "Euler's number, the base of the natural logarithm"),
+    ("phi", Value::Float(1.618033988749895, 0.0), "φ, the golden ratio"),
+    ("i8min", Value::Int(i8::MIN as i64), "the smallest signed 8 bit integer"),
+    ("i8max", Value::Int(i8::MAX as i64), "the largest signed 8 bit integer"),
+    ("i16min", Value::Int(i16::MIN as i64), "the smallest signed 16 bit integer"),
+    ("i16max", Value::Int(i16::MAX as i64), "the largest signed 16 bit integer"),
+    ("i32min", Value::Int(i32::MIN as i64), "the smallest signed 32 bit integer"),
+    ("i32max", Value::Int(i32::MAX as i64), "the largest signed 32 bit integer"),
+    ("i64min", Value::Int(i64::MIN), "the smallest signed 64 bit integer"),
+    ("i64max", Value::Int(i64::MAX), "the largest signed 64 bit integer"),
+    ("u8max", Value::Int(u8::MAX as i64), "the largest unsigned 8 bit integer"),
+    ("u16max", Value::Int(u16::MAX as i64), "the largest unsigned 16 bit integer"),
+    ("u32max", Value::Int(u32::MAX as i64), "the largest unsigned 32 bit integer"),
+];
+
+/// The value of the constant `name`, built in or from `state`.
+fn constant(name: &str, state: &State) -> Option<Value> {
+    let builtin = CONSTANTS.iter().find(|c| c.0 == name).map(|c| match c.1 {
+        // the floats are irrational, so the nearest float is up to half an
+        // ulp off
+        Value::Float(x, _) => Value::Float(x, ulp(x) / 2.0),
+        x => x,
+    });
+    builtin.or_else(|| state.constants.get(name).copied())
+}
+
+/// The forms the evaluator understands besides [`WORDS`], as an example
+/// and a description.
+pub static SYNTAX: &[(&str, &str)] = &[
+    ("12 -3 0xff", "integer, hex literals switch the display to hex"),
+    ("1.5 2.5e-3 1e9", "float"),
+    ("1_000_000", "decimals may be grouped with _ too"),
+    ("4k 1.5M 64Ki", "SI and IEC multiples, k M G T P E and Ki Mi Gi Ti Pi Ei"),
+    ("500 MB", "a quantity, in B kB MB GB TB KiB MiB GiB TiB, s ns us ms min h day or Hz kHz MHz GHz"),
+    ("0x7fff_e3a0_1c40", "grouped hex, switches the display to addresses"),
+    ("7:45", "hours and minutes, as minutes, switches the display to h:mm"),
+    ("2024-06-01T12:00:00Z", "a datetime, as unix time, switches the display to datetimes"),
+    ("2h30m 90m 1d", "a duration, as seconds, switches the display to durations"),
+    ("'a' U+1F600", "a character, as its code point, switches the display to characters"),
+    ("192.168.1.17", "an IPv4 address, as an integer, switches the display to addresses"),
+    ("10.0.0.0/8", "a subnet, an address and the length of its prefix"),
+    ("1/3", "an exact fraction, like dividing integers in .q"),
+    ("#ff8800", "a color, as an integer, switches the display to colors"),
+    ("x\"dead beef\" b64\"aGk=\"", "bytes in hex or base64, a value each, switches the display to hex"),
+    ("3d6 1d20+4 4d6*", "roll dice and push the total, or each roll with *"),
+    ("/+", "fold the whole stack with an operator"),
+    ("=(1920*1080*4)/1048576", "an infix expression, with + - * / % ² and parentheses, and calls like gcd(12, 18), where 7/2 is 3.5"),
+    (">name", "pop into a variable"),
+    ("$name", "push a variable"),
+    ("@3", "push the top of the third result"),
+    ("@3*", "push the whole stack of the third result"),
+];
+
+/// What a [`Token`] of the input is, as far as can be told without
+/// evaluating it.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TokenKind {
+    Number,
+    /// A word like `+` or `round`, or a fold like `/+`.
+    Operator,
+    /// A word starting with `.`, like `.h`.
+    Command,
+    /// What follows a word that takes arguments, like the `3` of `.prec 3`.
+    Argument,
+    /// `>name` or `$name`.
+    Variable,
+    /// `@3` or `@3*`.
+    Result,
+    /// A unit like `MB`.
+    Unit,
+    Unknown,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Where the token is in the input, in bytes.
+    pub span: std::ops::Range<usize>,
+}
+
+/// Split `inp` into whitespace separated tokens the way [`eval`] does,
+/// and say what each one is, with the constants of `state`.
+pub fn tokens(inp: &str, state: &State) -> Vec<Token> {
+    let infix = infix_expr(inp);
+    let rpn = infix.map_or(inp, |(offset, _)| &inp[..offset - 1]);
+
+    let mut out = vec![];
+    let mut args = 0;
+    for (span, x) in words(rpn) {
+        let kind = if args > 0 {
+            args -= 1;
+            TokenKind::Argument
+        } else {
+            args = lookup(x).map_or(0, |w| w.args().len());
+            token_kind(x, state)
+        };
+        out.push(Token { kind, span });
+    }
+    if let Some((offset, expr)) = infix {
+        out.push(Token { kind: TokenKind::Operator, span: offset - 1..offset });
+        for (span, x) in infix_pieces(expr, offset) {
+            let kind = if INFIX_OPS.contains(x) { TokenKind::Operator } else { token_kind(x, state) };
+            out.push(Token { kind, span });
+        }
+    }
+    out
+}
+
+fn token_kind(x: &str, state: &State) -> TokenKind {
+    if let Some(base) = x.strip_suffix('²').filter(|base| !base.is_empty()) {
+        return token_kind(base, state);
+    }
+    let ascii = x.is_ascii() || parse_char(x).is_some();
+    if ascii && (parse_number(x).is_some() || parse_bytes(x).is_some_and(|bytes| bytes.is_ok()) || parse_dice(x).is_some()) {
+        TokenKind::Number
+    } else if lookup(x).is_some() {
+        if x.starts_with('.') { TokenKind::Command } else { TokenKind::Operator }
+    } else if constant(x, state).is_some() {
+        TokenKind::Number
+    } else if find_unit(x).is_some() {
+        TokenKind::Unit
+    } else if x.strip_prefix('/').is_some_and(|op| operator(op).is_some()) {
+        TokenKind::Operator
+    } else if x.len() > 1 && (x.starts_with('>') || x.starts_with('$')) {
+        TokenKind::Variable
+    } else if x.strip_prefix('@').is_some_and(|n| n.strip_suffix('*').unwrap_or(n).parse::<usize>().is_ok()) {
+        TokenKind::Result
+    } else {
+        TokenKind::Unknown
+    }
+}
+
+/// What `prefix` could be completed to: words, units and constants, or
+/// with a leading `$` or `>` the variables of `state`, in sorted order.
+pub fn complete(prefix: &str, state: &State) -> Vec<String> {
+    let mut out = match prefix.chars().next() {
+        Some(sigil @ ('$' | '>')) => state.vars.keys().map(|name| format!("{sigil}{name}")).collect(),
+        _ => WORDS.iter().flat_map(|w| w.names).chain(UNITS.iter().map(|unit| &unit.name))
+            .chain(CONSTANTS.iter().map(|c| &c.0))
+            .map(|&name| name.to_owned()).chain(state.constants.keys().cloned()).collect::<Vec<_>>(),
+    };
+    out.retain(|name| name.starts_with(prefix));
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn lookup(name: &str) -> Option<&'static Word> {
+    WORDS.iter().find(|w| w.names.contains(&name))
+}
+
+fn word<'a>(x: &str, args: &mut impl Iterator<Item = &'a str>, state: &mut State) -> Result<(), EvalError> {
+    // 3², $x²
+    if let Some(base) = x.strip_suffix('²').filter(|base| !base.is_empty()) {
+        word(base, args, state)?;
+        return square(state);
+    }
+    // the literals are ASCII but for characters, and their parsers count
+    // on it
+    let ascii = x.is_ascii() || parse_char(x).is_some();
+
+    if ascii && let Some(num) = parse_number(x) {
+        // hex, address, datetime, h:mm, duration, character, IPv4 and color
+        // literals switch the display to match
+        if x.starts_with("0x") && x.contains('_') {
+            state.int_format = IntFormat::Addr;
+        } else if x.starts_with("0x") {
+            state.int_format = IntFormat::Hex;
+        } else if parse_datetime(x).is_some() {
+            state.int_format = IntFormat::Time;
+        } else if x.contains(':') {
+            state.int_format = IntFormat::HhMm;
+        } else if parse_duration(x).is_some() {
+            state.int_format = IntFormat::Duration;
+        } else if parse_char(x).is_some() {
+            state.int_format = IntFormat::Char;
+        } else if parse_ipv4(x).is_some() {
+            state.int_format = IntFormat::Ip;
+        } else if x.starts_with('#') {
+            state.int_format = IntFormat::Rgb;
+        }
+        state.stack.push(state.wrap(num));
+        return Ok(());
+    }
+
+    if ascii && let Some(bytes) = parse_bytes(x) {
+        let bytes = bytes?.into_iter().map(|byte| state.wrap(Value::Int(byte as i64))).collect::<Vec<_>>();
+        state.stack.extend(bytes);
+        state.int_format = IntFormat::Hex;
+        return Ok(());
+    }
+
+    if ascii && let Some(dice) = parse_dice(x) {
+        return roll(state, dice);
+    }
+
+    if let Some(w) = lookup(x) {
+        return match w.run {
+            Run::Plain(run) => run(state),
+            Run::Arg(names, run) => {
+                let args = args.take(names.len()).collect::<Vec<_>>();
+                if args.len() < names.len() {
+                    let names = names.iter().map(|name| format!("<{name}>")).collect::<Vec<_>>();
+                    return Err(EvalError::new(format!("{x} needs {}", names.join(" "))));
+                }
+                run(state, &args)
+            },
+        };
+    }
+
+    if let Some(value) = constant(x, state) {
+        state.stack.push(state.wrap(value));
+        return Ok(());
+    }
+
+    if let Some(unit) = find_unit(x) {
+        return attach_unit(state, unit);
+    }
+
+    // fold, /op, ( a b .. x --- a op b op .. op x )
+    if let Some(rest) = x.strip_prefix('/') {
+        let Some(op) = operator(rest) else {
+            return Err(EvalError::new("/<op>"));
+        };
+        let mut values = state.stack.iter().copied();
+        if let Some(first) = values.next() {
+            let res = values.try_fold(first, |acc, x| Ok::<_, EvalError>(state.wrap(op.apply(acc, x, state.rational)?)))?;
+            state.stack.truncate(0);
+            state.stack.push(res);
+        }
+        return Ok(());
+    }
+
+    if let Some(name) = x.strip_prefix('>') && !name.is_empty() {
+        let Some(value) = state.stack.pop() else {
+            return Err(EvalError::new(format!("{x} needs a number")));
+        };
+        state.vars.insert(name.to_owned(), value);
+        return Ok(());
+    }
+
+    if let Some(name) = x.strip_prefix('$') && !name.is_empty() {
+        let Some(&value) = state.vars.get(name) else {
+            return Err(EvalError::new(format!("no variable '{name}'")));
+        };
+        state.stack.push(value);
+        return Ok(());
+    }
+
+    if let Some(rest) = x.strip_prefix('@') {
+        let (n, all) = match rest.strip_suffix('*') {
+            Some(n) => (n, true),
+            None => (rest, false),
+        };
+        let Some(result) = n.parse::<usize>().ok().and_then(|n| state.results.get(n.wrapping_sub(1))) else {
+            return Err(EvalError::new(format!("no result @{n}")));
+        };
+        match (all, result.last()) {
+            (true, _) => state.stack.extend_from_slice(result),
+            (false, Some(&top)) => state.stack.push(top),
+            (false, None) => return Err(EvalError::new(format!("result @{n} is empty"))),
+        }
+        return Ok(());
+    }
+
+    Err(EvalError::new(format!("couldn't parse '{x}'")))
+}
+
+// sq, ( x --- x*x )
+fn square(state: &mut State) -> Result<(), EvalError> {
+    let Some(&x) = state.stack.last() else {
+        return Err(EvalError::new("sq needs a number"));
+    };
+    state.stack.push(x);
+    binary(state, Op::Mul)
+}
+
+fn binary(state: &mut State, op: Op) -> Result<(), EvalError> {
+    let [.., b, a] = state.stack[..] else {
+        return Err(EvalError::new(format!("{} needs two numbers", op.name())));
+    };
+    let res = state.wrap(op.apply(b, a, state.rational)?);
+    let stack = &mut state.stack;
+    stack.truncate(stack.len() - 2);
+    stack.push(res);
+    Ok(())
+}
+
+// %of, pct+, pct-, pctchg, ( a b --- x )
+fn percent(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let [.., a, b] = state.stack[..] else {
+        return Err(EvalError::new(format!("{name} needs two numbers")));
+    };
+    // percentages are rarely whole, so these are floats unless exact
+    let exact = state.rational;
+    let hundred = if exact { Value::Int(100) } else { Value::Float(100.0, 0.0) };
+    let of = |a, b| Op::Div.apply(Op::Mul.apply(a, b, exact)?, hundred, exact);
+    let neg = |x| Op::Mul.apply(x, Value::Int(-1), exact);
+    let res = match name {
+        "%of" => of(a, b)?,
+        "pct+" => Op::Add.apply(a, of(a, b)?, exact)?,
+        "pct-" => Op::Add.apply(a, neg(of(a, b)?)?, exact)?,
+        _ => {
+            let change = Op::Add.apply(b, neg(a)?, exact)?;
+            Op::Div.apply(Op::Mul.apply(change, hundred, exact)?, a, exact)?
+        },
+    };
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(state.wrap(res));
+    Ok(())
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// Square and multiply, in 128 bits so the products can't overflow.
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut res = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = mul_mod(res, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    res
+}
+
+/// Miller-Rabin, with the bases that are enough for all of 64 bits.
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = BASES.iter().find(|&&p| n % p == 0) {
+        return n == p;
+    }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'witness: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// A factor of `n` other than 1 and `n`, which must be odd and composite,
+/// with Pollard's rho.
+fn rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// The prime factors of `n`, with repeats, in no particular order.
+fn factorize(n: u64, out: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if n % 2 == 0 {
+        out.push(2);
+        return factorize(n / 2, out);
+    }
+    if is_prime(n) {
+        out.push(n);
+        return;
+    }
+    let d = rho(n);
+    factorize(d, out);
+    factorize(n / d, out);
+}
+
+// isprime, nextprime, ( n --- x )
+fn prime(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let Some(Value::Int(n)) = state.stack.last().map(|x| x.num()) else {
+        return Err(EvalError::new(format!("{name} needs an integer")));
+    };
+    let res = match name {
+        "isprime" => Value::Bool(n > 0 && is_prime(n as u64)),
+        _ => {
+            let next = (n.max(1) as u64 + 1..).find(|&p| is_prime(p)).filter(|&p| p <= i64::MAX as u64);
+            fits(state, name, next.map(u128::from))?
+        },
+    };
+    *state.stack.last_mut().unwrap() = res;
+    Ok(())
+}
+
+// factor, ( n --- p q .. )
+fn factor(state: &mut State) -> Result<(), EvalError> {
+    let n = match state.stack.last().map(|x| x.num()) {
+        Some(Value::Int(n)) if n > 0 => n as u64,
+        Some(_) => return Err(EvalError::new("factor needs a positive integer")),
+        None => return Err(EvalError::new("factor needs a number")),
+    };
+    let mut factors = vec![];
+    factorize(n, &mut factors);
+    factors.sort_unstable();
+    state.stack.pop();
+    state.stack.extend(factors.into_iter().map(|p| Value::Int(p as i64)));
+    Ok(())
+}
+
+/// How `a` compares to `b` for `name`, `None` if either is NaN.
+fn order(name: &str, a: Value, b: Value) -> Result<Option<Ordering>, EvalError> {
+    Ok(match (a.num(), b.num()) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(&b)),
+        (a @ (Value::Int(_) | Value::Ratio(..)), b @ (Value::Int(_) | Value::Ratio(..))) => {
+            let ((an, ad), (bn, bd)) = (a.as_ratio().unwrap(), b.as_ratio().unwrap());
+            Some((an * bd).cmp(&(bn * ad)))
+        },
+        (Value::Cidr(a, n), Value::Cidr(b, m)) => Some((a, n).cmp(&(b, m))),
+        (Value::Quantity(a, unit_a), Value::Quantity(b, unit_b)) if unit_a.dim == unit_b.dim => {
+            (a * unit_a.scale).partial_cmp(&(b * unit_b.scale))
+        },
+        (Value::Quantity(..), _) | (_, Value::Quantity(..)) => {
+            return Err(EvalError::new(format!("{name} needs the same kind of unit on both sides")));
+        },
+        (Value::Cidr(..), _) | (_, Value::Cidr(..)) => {
+            return Err(EvalError::new(format!("{name} can't compare a subnet with a number")));
+        },
+        (a, b) => a.to_float().0.partial_cmp(&b.to_float().0),
+    })
+}
+
+// =, <, >, <=, >=, ( x y --- b )
+fn compare(state: &mut State, name: &str, test: fn(Ordering) -> bool) -> Result<(), EvalError> {
+    let [.., a, b] = state.stack[..] else {
+        return Err(EvalError::new(format!("{name} needs two numbers")));
+    };
+    let ord = order(name, a, b)?;
+    state.stack.truncate(state.stack.len() - 2);
+    // NaN isn't anything compared to anything
+    state.stack.push(Value::Bool(ord.is_some_and(test)));
+    Ok(())
+}
+
+// sort, rsort, ( .. --- .. )
+fn sort(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let mut err = None;
+    // NaN after everything else, so the order is total
+    let nan = |x: &Value| x.to_float().0.is_nan();
+    let mut sorted = state.stack.clone();
+    sorted.sort_by(|a, b| match order(name, *a, *b) {
+        Ok(ord) => ord.unwrap_or_else(|| nan(a).cmp(&nan(b))),
+        Err(e) => {
+            err.get_or_insert(e);
+            Ordering::Equal
+        },
+    });
+    if let Some(e) = err {
+        return Err(e);
+    }
+    if name == "rsort" {
+        sorted.reverse();
+    }
+    state.stack = sorted;
+    Ok(())
+}
+
+// uniq, ( .. --- .. )
+fn uniq(state: &mut State) {
+    let mut seen: Vec<Value> = vec![];
+    for x in std::mem::take(&mut state.stack) {
+        if !seen.iter().any(|&y| order("uniq", x, y).is_ok_and(|ord| ord == Some(Ordering::Equal))) {
+            seen.push(x);
+        }
+    }
+    state.stack = seen;
+}
+
+// sel, ( c a b --- x )
+fn select(state: &mut State) -> Result<(), EvalError> {
+    let [.., c, a, b] = state.stack[..] else {
+        return Err(EvalError::new("sel needs a condition and two values"));
+    };
+    let c = match c.num() {
+        Value::Int(c) => c != 0,
+        Value::Float(c, _) => c != 0.0,
+        _ => return Err(EvalError::new("sel needs a number as the condition")),
+    };
+    state.stack.truncate(state.stack.len() - 3);
+    state.stack.push(if c { a } else { b });
+    Ok(())
+}
+
+// gcd, lcm, ( a b --- x )
+fn gcd_lcm(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let [.., a, b] = state.stack[..] else {
+        return Err(EvalError::new(format!("{name} needs two integers")));
+    };
+    let (Value::Int(a), Value::Int(b)) = (a.num(), b.num()) else {
+        return Err(EvalError::new(format!("{name} needs integers")));
+    };
+    let (a, b) = (a.unsigned_abs(), b.unsigned_abs());
+    let res = match name {
+        "gcd" => Some(gcd(a, b)),
+        _ if a == 0 || b == 0 => Some(0),
+        _ => (a / gcd(a, b)).checked_mul(b),
+    };
+    let Some(res) = res.and_then(|res| i64::try_from(res).ok()) else {
+        return Err(EvalError::new(format!("{name} overflowed")));
+    };
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(state.wrap(Value::Int(res)));
+    Ok(())
+}
+
+// powmod, ( a b m --- a^b%m )
+fn powmod(state: &mut State) -> Result<(), EvalError> {
+    let [.., a, b, m] = state.stack[..] else {
+        return Err(EvalError::new("powmod needs a base, an exponent and a modulus"));
+    };
+    let (Value::Int(a), Value::Int(b), Value::Int(m)) = (a.num(), b.num(), m.num()) else {
+        return Err(EvalError::new("powmod needs integers"));
+    };
+    if b < 0 || m <= 0 {
+        return Err(EvalError::new(format!("powmod needs a non-negative exponent and a positive modulus, not {b} {m}")));
+    }
+    let res = pow_mod(a.rem_euclid(m) as u64, b as u64, m as u64);
+    state.stack.truncate(state.stack.len() - 3);
+    state.stack.push(state.wrap(Value::Int(res as i64)));
+    Ok(())
+}
+
+// invmod, ( a m --- x )
+fn invmod(state: &mut State) -> Result<(), EvalError> {
+    let [.., a, m] = state.stack[..] else {
+        return Err(EvalError::new("invmod needs a number and a modulus"));
+    };
+    let (Value::Int(a), Value::Int(m)) = (a.num(), m.num()) else {
+        return Err(EvalError::new("invmod needs integers"));
+    };
+    if m <= 0 {
+        return Err(EvalError::new(format!("invmod needs a positive modulus, not {m}")));
+    }
+    // extended Euclid, keeping only the coefficients of a
+    let (mut r0, mut r1) = (a.rem_euclid(m) as i128, m as i128);
+    let (mut x0, mut x1) = (1i128, 0i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (x0, x1) = (x1, x0 - q * x1);
+    }
+    if r0 != 1 {
+        return Err(EvalError::new(format!("{a} has no inverse mod {m}, they share the factor {r0}")));
+    }
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(state.wrap(Value::Int(x0.rem_euclid(m as i128) as i64)));
+    Ok(())
+}
+
+/// `res` if it fits in the word size, otherwise an error naming `name`.
+fn fits(state: &State, name: &str, res: Option<u128>) -> Result<Value, EvalError> {
+    let fit = res.and_then(|res| i64::try_from(res).ok()).map(Value::Int).filter(|&res| state.wrap(res) == res);
+    fit.ok_or_else(|| EvalError::new(format!("{name} overflowed {} bits", state.bits())))
+}
+
+// !, ( n --- n! )
+fn factorial(state: &mut State) -> Result<(), EvalError> {
+    let n = match state.stack.last().map(|x| x.num()) {
+        Some(Value::Int(n)) if n >= 0 => n as u128,
+        Some(_) => return Err(EvalError::new("! needs a non-negative integer")),
+        None => return Err(EvalError::new("! needs a number")),
+    };
+    let res = (1..=n).try_fold(1u128, |acc, i| acc.checked_mul(i));
+    *state.stack.last_mut().unwrap() = fits(state, "!", res)?;
+    Ok(())
+}
+
+// ncr, npr, ( n k --- x )
+fn choose(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let [.., n, k] = state.stack[..] else {
+        return Err(EvalError::new(format!("{name} needs n and k")));
+    };
+    let (Value::Int(n), Value::Int(k)) = (n.num(), k.num()) else {
+        return Err(EvalError::new(format!("{name} needs integers")));
+    };
+    if n < 0 || !(0..=n).contains(&k) {
+        return Err(EvalError::new(format!("{name} needs 0 <= k <= n, not {n} {k}")));
+    }
+    let (n, k) = (n as u128, k as u128);
+    let res = match name {
+        // each step is itself a binomial coefficient, so divides exactly
+        "ncr" => (0..k.min(n - k)).try_fold(1u128, |acc, i| Some(acc.checked_mul(n - i)? / (i + 1))),
+        _ => (n - k + 1..=n).try_fold(1u128, |acc, i| acc.checked_mul(i)),
+    };
+    let res = fits(state, name, res)?;
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(res);
+    Ok(())
+}
+
+// abs, sign, neg, ( x --- y )
+fn unary(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let sign = |x: f64| if x > 0.0 { 1 } else if x < 0.0 { -1 } else { 0 };
+    let res = match (name, state.stack.last().map(|x| x.num())) {
+        (_, None) => return Err(EvalError::new(format!("{name} needs a number"))),
+        ("abs", Some(Value::Int(x))) => Value::Int(x.wrapping_abs()),
+        ("abs", Some(Value::Float(x, err))) => Value::Float(x.abs(), err),
+        ("abs", Some(Value::Quantity(x, unit))) => Value::Quantity(x.abs(), unit),
+        ("abs", Some(Value::Ratio(n, d))) => ratio((n as i128).abs(), d as i128)?,
+        ("sign", Some(Value::Int(x) | Value::Ratio(x, _))) => Value::Int(x.signum()),
+        ("sign", Some(Value::Float(x, _))) if x.is_nan() => Value::Float(x, 0.0),
+        ("sign", Some(Value::Float(x, _) | Value::Quantity(x, _))) => Value::Int(sign(x)),
+        ("neg", Some(Value::Int(x))) => Value::Int(x.wrapping_neg()),
+        ("neg", Some(Value::Float(x, err))) => Value::Float(-x, err),
+        ("neg", Some(Value::Quantity(x, unit))) => Value::Quantity(-x, unit),
+        ("neg", Some(Value::Ratio(n, d))) => ratio(-(n as i128), d as i128)?,
+        (_, Some(_)) => return Err(EvalError::new(format!("{name} needs a number"))),
+    };
+    *state.stack.last_mut().unwrap() = state.wrap(res);
+    Ok(())
+}
+
+// iota, ( n --- 1 .. n )
+fn iota(state: &mut State) -> Result<(), EvalError> {
+    let stack = &mut state.stack;
+    match stack.last().map(|x| x.num()) {
+        Some(Value::Int(count)) => {
+            stack.pop();
+            stack.extend((1..=count).map(Value::Int));
+            Ok(())
+        },
+        Some(_) => Err(EvalError::new("i needs an integer")),
+        None => Err(EvalError::new("i needs a number")),
+    }
+}
+
+// NdM, ( --- total ), NdM*, ( --- a b .. )
+fn roll(state: &mut State, (count, sides, modifier, each): (u64, u64, i64, bool)) -> Result<(), EvalError> {
+    if count > 1000 {
+        return Err(EvalError::new("at most 1000 dice at a time"));
+    }
+    let rolls = (0..count).map(|_| state.random_below(sides) as i128 + 1).collect::<Vec<_>>();
+    // like fits, but the modifier can make a total negative
+    let fit = |state: &State, x: i128| {
+        let fit = i64::try_from(x).ok().map(Value::Int).filter(|&x| state.wrap(x) == x);
+        fit.ok_or_else(|| EvalError::new(format!("the roll overflowed {} bits", state.bits())))
+    };
+    let mut res = vec![];
+    if each {
+        for x in rolls {
+            res.push(fit(state, x)?);
+        }
+        if modifier != 0 {
+            res.push(fit(state, modifier as i128)?);
+        }
+    } else {
+        res.push(fit(state, rolls.iter().sum::<i128>() + modifier as i128)?);
+    }
+    state.stack.extend(res);
+    Ok(())
+}
+
+// random, ( n --- 0 <= x < n )
+fn rand(state: &mut State) -> Result<(), EvalError> {
+    let res = match state.stack.last().map(|x| x.num()) {
+        Some(Value::Int(n)) if n > 0 => Value::Int(state.random_below(n as u64) as i64),
+        Some(Value::Float(n, _)) if n > 0.0 => {
+            Value::Float((state.random() >> 11) as f64 / (1u64 << 53) as f64 * n, 0.0)
+        },
+        Some(_) => return Err(EvalError::new("rand needs a positive bound")),
+        None => return Err(EvalError::new("rand needs a number")),
+    };
+    *state.stack.last_mut().unwrap() = res;
+    Ok(())
+}
+
+// bswap16, bswap32, bswap64, ( x --- x' )
+fn byte_swap(state: &mut State, bits: u32) -> Result<(), EvalError> {
+    let Some(Value::Int(x)) = state.stack.last().map(|x| x.num()) else {
+        return Err(EvalError::new(format!("bswap{bits} needs an integer")));
+    };
+    if bits > state.bits() {
+        return Err(EvalError::new(format!("bswap{bits} doesn't fit in {} bits", state.bits())));
+    }
+    let swapped = state.unsigned(x).swap_bytes() >> (64 - bits);
+    *state.stack.last_mut().unwrap() = state.wrap(Value::Int(swapped as i64));
+    Ok(())
+}
+
+// popcount, clz, ctz, parity, ( x --- n )
+fn count_bits(state: &mut State, name: &str, count: fn(u64, u32) -> u32) -> Result<(), EvalError> {
+    let Some(Value::Int(x)) = state.stack.last().map(|x| x.num()) else {
+        return Err(EvalError::new(format!("{name} needs an integer")));
+    };
+    *state.stack.last_mut().unwrap() = Value::Int(count(state.unsigned(x), state.bits()) as i64);
+    Ok(())
+}
+
+/// The field `hi..=lo` of `bits` and the mask of it, for `bits` and
+/// `setbits`.
+fn field(state: &State, name: &str, hi: Value, lo: Value) -> Result<(u32, u64), EvalError> {
+    let (Value::Int(hi), Value::Int(lo)) = (hi.num(), lo.num()) else {
+        return Err(EvalError::new(format!("{name} needs integer bit positions")));
+    };
+    if lo < 0 || hi < lo || hi >= state.bits() as i64 {
+        return Err(EvalError::new(format!("{name} needs 0 <= lo <= hi < {}, not {hi} {lo}", state.bits())));
+    }
+    let width = (hi - lo + 1) as u32;
+    Ok((lo as u32, (u64::MAX >> (64 - width)) << lo))
+}
+
+// bits, ( x hi lo --- field )
+fn extract_bits(state: &mut State) -> Result<(), EvalError> {
+    let [.., x, hi, lo] = state.stack[..] else {
+        return Err(EvalError::new("bits needs a number and the positions of its highest and lowest bits"));
+    };
+    let Value::Int(x) = x.num() else {
+        return Err(EvalError::new("bits needs an integer"));
+    };
+    let (shift, mask) = field(state, "bits", hi, lo)?;
+    let res = (state.unsigned(x) & mask) >> shift;
+    state.stack.truncate(state.stack.len() - 3);
+    state.stack.push(state.wrap(Value::Int(res as i64)));
+    Ok(())
+}
+
+// setbits, ( x hi lo v --- x' )
+fn set_bits(state: &mut State) -> Result<(), EvalError> {
+    let [.., x, hi, lo, v] = state.stack[..] else {
+        return Err(EvalError::new("setbits needs a number, the positions of the highest and lowest bits and their value"));
+    };
+    let (Value::Int(x), Value::Int(v)) = (x.num(), v.num()) else {
+        return Err(EvalError::new("setbits needs integers"));
+    };
+    let (shift, mask) = field(state, "setbits", hi, lo)?;
+    if v < 0 || v as u64 > mask >> shift {
+        return Err(EvalError::new(format!("{v} doesn't fit in {} bits", mask.count_ones())));
+    }
+    let res = state.unsigned(x) & !mask | (v as u64) << shift;
+    state.stack.truncate(state.stack.len() - 4);
+    state.stack.push(state.wrap(Value::Int(res as i64)));
+    Ok(())
+}
+
+// mask, ( n --- mask )
+fn mask(state: &mut State) -> Result<(), EvalError> {
+    let Some(Value::Int(n)) = state.stack.last().map(|x| x.num()) else {
+        return Err(EvalError::new("mask needs an integer number of bits"));
+    };
+    if !(0..=state.bits() as i64).contains(&n) {
+        return Err(EvalError::new(format!("mask needs 0 to {} bits, not {n}", state.bits())));
+    }
+    let res = u64::MAX.checked_shr(64 - n as u32).unwrap_or(0);
+    *state.stack.last_mut().unwrap() = state.wrap(Value::Int(res as i64));
+    Ok(())
+}
+
+// f2b, ( x --- bits )
+fn float_to_bits(state: &mut State) -> Result<(), EvalError> {
+    let Some(x) = state.stack.pop() else {
+        return Err(EvalError::new("f2b needs a number"));
+    };
+    state.stack.push(state.wrap(Value::Int(x.to_float().0.to_bits() as i64)));
+    state.int_format = IntFormat::Hex;
+    Ok(())
+}
+
+// b2f, ( bits --- x )
+fn bits_to_float(state: &mut State) -> Result<(), EvalError> {
+    let Some(Value::Int(bits)) = state.stack.last().map(|x| x.num()) else {
+        return Err(EvalError::new("b2f needs the bits as an integer"));
+    };
+    *state.stack.last_mut().unwrap() = Value::Float(f64::from_bits(state.unsigned(bits)), 0.0);
+    Ok(())
+}
+
+// ieee, ( x --- sign exponent mantissa )
+fn ieee(state: &mut State) -> Result<(), EvalError> {
+    let Some(x) = state.stack.pop() else {
+        return Err(EvalError::new("ieee needs a number"));
+    };
+    let bits = x.to_float().0.to_bits();
+    state.stack.push(Value::Int((bits >> 63) as i64));
+    state.stack.push(Value::Int((bits >> 52 & 0x7ff) as i64));
+    state.stack.push(Value::Int((bits & ((1 << 52) - 1)) as i64));
+    Ok(())
+}
+
+// now, ( --- t )
+fn now(state: &mut State) -> Result<(), EvalError> {
+    let Some(clock) = state.clock else {
+        return Err(EvalError::new("there is no clock here"));
+    };
+    state.stack.push(Value::Int(clock()));
+    Ok(())
+}
+
+// off, ( a b --- b-a )
+fn offset(state: &mut State) -> Result<(), EvalError> {
+    let [.., Value::Int(a), Value::Int(b)] = state.stack[..] else {
+        return Err(EvalError::new("off needs two addresses"));
+    };
+    let off = b.checked_sub(a).ok_or_else(|| EvalError::new("off overflowed"))?;
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(state.wrap(Value::Int(off)));
+    state.int_format = IntFormat::Bytes;
+    Ok(())
+}
+
+// is_p2, next_p2, prev_p2, log2, log10, ( x --- y )
+fn power_of_two(state: &mut State, name: &str, f: fn(i64) -> Option<Value>) -> Result<(), EvalError> {
+    let x = match state.stack.last().map(|x| x.num()) {
+        Some(Value::Int(x)) if x > 0 => x,
+        Some(Value::Int(x)) => return Err(EvalError::new(format!("{name} needs a positive integer, not {x}"))),
+        Some(_) => return Err(EvalError::new(format!("{name} needs an integer"))),
+        None => return Err(EvalError::new(format!("{name} needs a number"))),
+    };
+    let res = match f(x) {
+        Some(Value::Int(res)) => fits(state, name, u128::try_from(res).ok())?,
+        Some(res) => res,
+        // next_p2 past 64 bits
+        None => fits(state, name, None)?,
+    };
+    *state.stack.last_mut().unwrap() = res;
+    Ok(())
+}
+
+// crc32, fnv1a, sha256, ( bytes.. --- digest )
+fn hash(state: &mut State, name: &str) -> Result<(), EvalError> {
+    // the stack is the bytes, or a lone value bigger than a byte stands
+    // for its own bytes
+    let bytes = match state.stack[..] {
+        [Value::Int(x)] if !(0..=255).contains(&x) => state.unsigned(x).to_le_bytes()[..state.bits() as usize / 8].to_vec(),
+        _ => state.stack.iter().map(|&x| match x.num() {
+            // all of them fit in a byte in .w8
+            Value::Int(x) if (0..=255).contains(&x) || state.bits() == 8 => Ok(x as u8),
+            _ => Err(EvalError::new(format!("{name} needs bytes from 0 to 255, not {}", state.render_value(x)))),
+        }).collect::<Result<Vec<_>, _>>()?,
+    };
+    let (digest, bits) = match name {
+        "crc32" => (vec![crc32(&bytes) as i64], 32),
+        "fnv1a" => (vec![fnv1a(&bytes) as i64], 64),
+        _ => (sha256(&bytes).into_iter().map(i64::from).collect(), 8),
+    };
+    // a smaller word would cut the hash short, in a word its size the bits
+    // are all there even if it shows as negative
+    if state.bits() < bits {
+        return Err(EvalError::new(format!("{name} needs words of at least {bits} bits, not {}", state.bits())));
+    }
+    state.stack = digest.into_iter().map(|x| state.wrap(Value::Int(x))).collect();
+    state.int_format = IntFormat::Hex;
+    Ok(())
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+    // a 1 bit, zeros up to 8 bytes short of a block, and the length in bits
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ w[i - 15] >> 3;
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ w[i - 2] >> 10;
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (hh, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (out, h) in digest.chunks_mut(4).zip(h) {
+        out.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+// rgb, ( r g b --- color )
+fn rgb(state: &mut State) -> Result<(), EvalError> {
+    let [.., r, g, b] = state.stack[..] else {
+        return Err(EvalError::new("rgb needs red, green and blue"));
+    };
+    let mut color = 0;
+    for x in [r, g, b] {
+        match x.num() {
+            Value::Int(x @ 0..=255) => color = color << 8 | x,
+            _ => return Err(EvalError::new(format!("rgb needs components from 0 to 255, not {}", state.render_value(x)))),
+        }
+    }
+    state.stack.truncate(state.stack.len() - 3);
+    state.stack.push(state.wrap(Value::Int(color)));
+    state.int_format = IntFormat::Rgb;
+    Ok(())
+}
+
+// unrgb, ( color --- r g b )
+fn unrgb(state: &mut State) -> Result<(), EvalError> {
+    let color = match state.stack.last().map(|x| x.num()) {
+        Some(Value::Int(x @ 0..=0xffffff)) => x,
+        Some(_) => return Err(EvalError::new("unrgb needs a color from #000000 to #ffffff")),
+        None => return Err(EvalError::new("unrgb needs a color")),
+    };
+    state.stack.pop();
+    state.stack.extend([color >> 16, color >> 8 & 0xff, color & 0xff].map(Value::Int));
+    state.int_format = IntFormat::Dec;
+    Ok(())
+}
+
+/// The bits of the network prefix.
+fn netmask(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0)
+}
+
+// net, bcast, hosts, ( subnet --- x )
+fn subnet(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let Some(&Value::Cidr(addr, prefix)) = state.stack.last() else {
+        return Err(EvalError::new(format!("{name} needs a subnet, like 10.0.0.0/8")));
+    };
+    let mask = netmask(prefix);
+    let res = match name {
+        "net" => (addr & mask) as i64,
+        "bcast" => (addr | !mask) as i64,
+        // /31 and /32 have no network and broadcast addresses to leave out
+        _ => match prefix {
+            32 => 1,
+            31 => 2,
+            _ => (1i64 << (32 - prefix)) - 2,
+        },
+    };
+    state.int_format = if name == "hosts" { IntFormat::Dec } else { IntFormat::Ip };
+    *state.stack.last_mut().unwrap() = state.wrap(Value::Int(res));
+    Ok(())
+}
+
+// insub, ( addr subnet --- b )
+fn in_subnet(state: &mut State) -> Result<(), EvalError> {
+    let [.., addr, Value::Cidr(net, prefix)] = state.stack[..] else {
+        return Err(EvalError::new("insub needs an address and a subnet"));
+    };
+    let addr = match addr.num() {
+        Value::Int(addr) => u32::try_from(addr).ok(),
+        Value::Cidr(addr, _) => Some(addr),
+        _ => None,
+    };
+    let Some(addr) = addr else {
+        return Err(EvalError::new("insub needs an IPv4 address"));
+    };
+    let mask = netmask(prefix);
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(Value::Bool(addr & mask == net & mask));
+    Ok(())
+}
+
+// alup, aldn, aloff, ( x a --- x' )
+fn align(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let [.., x, a] = state.stack[..] else {
+        return Err(EvalError::new(format!("{name} needs a number and an alignment")));
+    };
+    let (Value::Int(x), Value::Int(a)) = (x.num(), a.num()) else {
+        return Err(EvalError::new(format!("{name} needs integers")));
+    };
+    if a <= 0 || a.count_ones() != 1 {
+        return Err(EvalError::new(format!("{name} needs a power of two to align to, not {a}")));
+    }
+    let (x, low) = (state.unsigned(x), a as u64 - 1);
+    let max = u64::MAX >> (64 - state.bits());
+    let res = match name {
+        "alup" => x.checked_add(low).filter(|&sum| sum <= max).ok_or_else(|| EvalError::new("alup overflowed"))? & !low,
+        "aldn" => x & !low,
+        _ => x & low,
+    };
+    state.stack.truncate(state.stack.len() - 2);
+    state.stack.push(state.wrap(Value::Int(res as i64)));
+    Ok(())
+}
+
+// sumtime, ( a b .. x --- a+b+..+x )
+fn sumtime(state: &mut State) -> Result<(), EvalError> {
+    let sum = state.stack.iter().try_fold(Value::Int(0), |acc, &x| Ok::<_, EvalError>(state.wrap(Op::Add.apply(acc, x, false)?)))?;
+    state.stack.clear();
+    state.stack.push(sum);
+    state.int_format = IntFormat::HhMm;
+    Ok(())
+}
+
+// clear, ( .. --- )
+fn clear(state: &mut State) -> Result<(), EvalError> {
+    if !state.stack.is_empty() {
+        state.effects.push(Effect::Discard(format!("{} values on the stack", state.stack.len())));
+    }
+    state.stack.clear();
+    Ok(())
+}
+
+// round, trunc, ( x n --- x' )
+fn round(state: &mut State, trunc: bool) -> Result<(), EvalError> {
+    let (name, to_int): (_, fn(f64) -> f64) = if trunc { ("trunc", f64::trunc) } else { ("round", f64::round) };
+    let stack = &mut state.stack;
+    let [.., x, n] = stack[..] else {
+        return Err(EvalError::new(format!("{name} needs a number and a count of decimals")));
+    };
+    let Value::Int(n) = n.num() else {
+        return Err(EvalError::new(format!("{name} needs an integer count of decimals")));
+    };
+
+    let round_float = |x: f64, err| {
+        let n = n.clamp(-308, MAX_DECIMALS as i64);
+        // past 308 in two steps, one power of ten that big is infinite
+        let (scale, more) = (10f64.powi(n.min(308) as i32), 10f64.powi((n - 308).max(0) as i32));
+        if !x.is_finite() || (x * scale * more).abs() >= (1u64 << 53) as f64 {
+            // no decimals left to drop
+            return (x, err);
+        }
+        let res = if n >= 0 { to_int(x * scale * more) / more / scale } else { to_int(x / (1.0 / scale)) * (1.0 / scale) };
+        if !res.is_finite() {
+            // rounded up past the largest float
+            return (x, err);
+        }
+        // the result stands for the decimal it was rounded to, so its error
+        // is just how far the float is from that decimal
+        parse_float(&format!("{res:.p$}", p = n.max(1) as usize)).unwrap()
+    };
+    let res = match x.num() {
+        Value::Int(x) if n >= 0 => Value::Int(x),
+        Value::Int(x) => {
+            let step = 10i128.checked_pow(n.unsigned_abs().min(u32::MAX as u64) as u32).unwrap_or(i128::MAX);
+            let (q, r) = ((x as i128) / step, (x as i128) % step);
+            let away = !trunc && 2 * r.abs() >= step;
+            let res = (q + if away { r.signum() } else { 0 }).checked_mul(step).and_then(|res| i64::try_from(res).ok());
+            Value::Int(res.ok_or_else(|| EvalError::new(format!("{name} overflowed 64 bits")))?)
+        },
+        Value::Float(x, err) => {
+            let (x, err) = round_float(x, err);
+            Value::Float(x, err)
+        },
+        Value::Bool(_) => unreachable!(),
+        Value::Quantity(x, unit) => Value::Quantity(round_float(x, 0.0).0, unit),
+        Value::Cidr(..) => return Err(EvalError::new(format!("{name} needs a number, not a subnet"))),
+        // exactly to a multiple of the power of ten
+        Value::Ratio(p, q) if n <= 0 => {
+            let step = 10i128.checked_pow(n.unsigned_abs().min(u32::MAX as u64) as u32);
+            let Some(den) = step.and_then(|step| step.checked_mul(q as i128)) else {
+                return Err(EvalError::new(format!("{name} can't round to {n} decimals")));
+            };
+            let (d, r) = (p as i128 / den, p as i128 % den);
+            let away = !trunc && 2 * r.abs() >= den;
+            let res = (d + if away { r.signum() } else { 0 }) * step.unwrap();
+            Value::Int(i64::try_from(res).map_err(|_| EvalError::new(format!("{name} overflowed 64 bits")))?)
+        },
+        Value::Ratio(..) => {
+            let (x, err) = round_float(x.to_float().0, 0.0);
+            Value::Float(x, err)
+        },
+    };
+    stack.truncate(stack.len() - 2);
+    stack.push(res);
+    Ok(())
+}
+
+/// More decimals than any float has: the smallest one, 5e-324, has
+/// its last significant digit at 324.
+const MAX_DECIMALS: usize = 340;
+
+// .prec <n>
+fn precision(state: &mut State, arg: &str) -> Result<(), EvalError> {
+    state.precision = match arg {
+        "off" => None,
+        _ => Some(arg.parse().ok().filter(|&p| p <= MAX_DECIMALS).ok_or_else(|| {
+            EvalError::new(format!(".prec needs a number of decimals up to {MAX_DECIMALS} or 'off', not '{arg}'"))
+        })?),
+    };
+    Ok(())
+}
+
+// .seed <n>
+fn seed(state: &mut State, arg: &str) -> Result<(), EvalError> {
+    let n = parse_number(arg).and_then(|x| match x {
+        Value::Int(n) => Some(n as u64),
+        _ => None,
+    });
+    state.rng = Some(n.ok_or_else(|| EvalError::new(format!(".seed needs an integer, not '{arg}'")))?);
+    Ok(())
+}
+
+/// Fails for words with side effects when `state` is sandboxed.
+fn side_effect(state: &State, word: &str) -> Result<(), EvalError> {
+    if state.sandbox {
+        return Err(EvalError::new(format!("{word} is disabled in the sandbox")));
+    }
+    Ok(())
+}
+
+// .save <name>
+fn save(state: &mut State, name: &str) -> Result<(), EvalError> {
+    side_effect(state, ".save")?;
+    let input = state.to_input();
+    state.effects.push(Effect::Save { name: name.to_owned(), input });
+    Ok(())
+}
+
+// .load <name>
+fn load(state: &mut State, name: &str) -> Result<(), EvalError> {
+    side_effect(state, ".load")?;
+    let Some(loader) = state.loader else {
+        return Err(EvalError::new("there are no sessions to load here"));
+    };
+    let input = loader(name).map_err(EvalError::new)?;
+    if !state.stack.is_empty() || !state.vars.is_empty() {
+        state.effects.push(Effect::Discard("the current stack and variables".into()));
+    }
+
+    // a session can't load another one, that could go on forever
+    let mut loaded = State {
+        rng: state.rng,
+        clock: state.clock,
+        constants: state.constants.clone(),
+        effects: state.effects.clone(),
+        ..State::default()
+    };
+    eval(&input, &mut loaded).map_err(|e| EvalError::new(format!("session '{name}': {e}")))?;
+    *state = State { loader: Some(loader), ..loaded };
+    state.effects.push(Effect::Loaded);
+    Ok(())
+}
+
+// .w8, .w16, .w32, .w64
+fn word_size(state: &mut State, bits: u32) -> Result<(), EvalError> {
+    let resized = State { word_size: bits, stack: vec![], workspaces: BTreeMap::new(), ..state.clone() };
+    // what no longer fits, as it was shown and as it is now, with the
+    // name of the stack when it's a parked one
+    let mut changed = vec![];
+    let mut wrap = |stack: &[Value], int_format, parked: Option<&str>| -> Vec<Value> {
+        let (before, after) = (State { int_format, word_size: state.word_size, ..resized.clone() }, State { int_format, ..resized.clone() });
+        stack.iter().map(|&x| {
+            let wrapped = after.wrap(x);
+            if wrapped != x {
+                let on = parked.map(|name| format!(" on {name}")).unwrap_or_default();
+                changed.push(format!("{}{on} is now {}", before.render_value(x), after.render_value(wrapped)));
+            }
+            wrapped
+        }).collect()
+    };
+    let stack = wrap(&state.stack, state.int_format, None);
+    let workspaces = state.workspaces.iter().map(|(name, parked)| {
+        let stack = wrap(&parked.stack, parked.int_format, Some(name));
+        (name.clone(), Workspace { stack, ..parked.clone() })
+    }).collect();
+    if state.strict && !changed.is_empty() {
+        return Err(EvalError::new(format!("not everything fits in {bits} bits: {}", changed.join(", "))));
+    }
+    if !changed.is_empty() {
+        state.effects.push(Effect::Warn(format!("{bits} bits: {}", changed.join(", "))));
+    }
+    state.stack = stack;
+    state.workspaces = workspaces;
+    state.word_size = bits;
+    Ok(())
+}
+
+// MB, s, Hz .., ( x --- x unit )
+fn attach_unit(state: &mut State, unit: &'static Unit) -> Result<(), EvalError> {
+    let res = match state.stack.last().map(|x| x.num()) {
+        Some(x @ Value::Quantity(..)) => {
+            let x = state.render_value(x);
+            return Err(EvalError::new(format!("{x} already has a unit, `to {}` converts it", unit.name)));
+        },
+        Some(x) => Value::Quantity(x.to_float().0, unit),
+        None => return Err(EvalError::new(format!("{} needs a number", unit.name))),
+    };
+    *state.stack.last_mut().unwrap() = res;
+    Ok(())
+}
+
+// to <unit>, ( x --- x' )
+fn convert(state: &mut State, name: &str) -> Result<(), EvalError> {
+    let Some(to) = find_unit(name) else {
+        return Err(EvalError::new(format!("no unit '{name}'")));
+    };
+    let Some(&Value::Quantity(x, from)) = state.stack.last() else {
+        return Err(EvalError::new("to needs a quantity, like 500 MB"));
+    };
+    if from.dim != to.dim {
+        return Err(EvalError::new(format!("can't convert {} to {}", from.name, to.name)));
+    }
+    *state.stack.last_mut().unwrap() = Value::Quantity(x * from.scale / to.scale, to);
+    Ok(())
+}
+
+// .stack <name>
+fn switch_stack(state: &mut State, name: &str) -> Result<(), EvalError> {
+    if name == state.workspace_name() {
+        return Ok(());
+    }
+    let parked = Workspace {
+        stack: std::mem::take(&mut state.stack),
+        vars: std::mem::take(&mut state.vars),
+        int_format: state.int_format,
+    };
+    state.workspaces.insert(state.workspace_name().to_owned(), parked);
+    let next = state.workspaces.remove(name).unwrap_or_default();
+    state.stack = next.stack;
+    state.vars = next.vars;
+    state.int_format = next.int_format;
+    state.workspace = (name != "main").then(|| name.to_owned());
+    Ok(())
+}
+
+// .top <first|last>
+fn top(state: &mut State, arg: &str) -> Result<(), EvalError> {
+    state.top_first = match arg {
+        "first" => true,
+        "last" => false,
+        _ => return Err(EvalError::new(format!(".top needs 'first' or 'last', not '{arg}'"))),
+    };
+    Ok(())
+}
+
+// .snip <save|insert> <name>, the frontend edits the input line, as a
+// snippet needn't evaluate on its own
+fn snip(state: &mut State, args: &[&str]) -> Result<(), EvalError> {
+    side_effect(state, ".snip")?;
+    match args[0] {
+        "save" | "insert" => Ok(()),
+        action => Err(EvalError::new(format!(".snip needs 'save' or 'insert', not '{action}'"))),
+    }
+}
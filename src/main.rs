@@ -1,233 +1,1360 @@
-#![feature(let_chains)]
+mod background;
+mod cache;
+mod cheatsheet;
+mod clipboard;
+mod completions;
+mod config;
+mod history;
+mod import;
+mod keys;
+mod server;
+mod session;
+mod sink;
+mod snippets;
+mod theme;
+mod tutor;
 
+use std::cell::{Cell, RefCell};
 use std::error::Error;
-use std::ops;
-use crossterm::event::{Event, KeyCode, KeyModifiers};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
 use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-fn operator(op: &str) -> Option<fn(i64,i64) -> i64> {
-    match op {
-        "p" | "+" => Some(ops::Add::add),
-        "m" | "*" => Some(ops::Mul::mul),
-        "d" => Some(ops::Div::div),
-        "%" => Some(ops::Rem::rem),
-        _ => None,
-    }
-}
+use c::{eval, Effect, EvalError, State};
+use background::Evaluator;
+use cache::Cache;
+use config::Config;
+use history::History;
+use import::Import;
+use keys::{Action, Keymap};
+use sink::Sink;
+use snippets::Snippets;
+use theme::Theme;
 
-#[derive(PartialEq, Eq, Clone, Copy, Default)]
-enum IntFormat {
-    #[default]
-    Dec,
-    Hex
+struct S {
+    input: Input,
+    // what each evaluation of the input starts from
+    base: State,
+    output: State,
+    // evaluates long inputs, output and err are out of date while it does
+    evaluator: Evaluator,
+    // evaluates the others, cleared whenever base changes
+    cache: Cache,
+    history: History,
+    // the history line shown in the input, and what was typed before
+    recall: Option<(usize, String)>,
+    // the last committed evaluation, saved on exit with --auto-session
+    committed: State,
+    auto_session: bool,
+    err: Option<EvalError>,
+    sinks: Vec<Sink>,
+    // status message shown in the error row until the next key press
+    msg: Option<String>,
+    // how long the last evaluation of the input took, shown after .time
+    took: Duration,
+    mode: Mode,
+    // the log of committed lines and results in full-screen mode, None
+    // when they go to the terminal's scrollback instead
+    log: Option<Vec<String>>,
+    // how many log lines are scrolled back
+    scroll: usize,
+    // ask before committing a line that throws something away
+    confirm: bool,
+    // start each line from the stack the last one left
+    accumulate: bool,
+    snippets: Snippets,
+    // rows of the error, the output and the input, at least 3
+    height: u16,
+    // how many columns of each of those rows the last frame took up
+    drawn: RefCell<Vec<usize>>,
+    // where the last frame put the input, and the first character shown
+    input_at: Cell<(Rect, usize)>,
+    // what repeated Tabs cycle through, which one is in the input, and
+    // where
+    completing: Option<(Vec<String>, usize, std::ops::Range<usize>)>,
+    // the lesson of `c tutor` being done
+    tutor: Option<usize>,
+    keys: Keymap,
+    theme: Theme,
+    // what base and committed were before each of the last commits, and
+    // the undone ones to redo
+    undo: Vec<(State, State)>,
+    redo: Vec<(State, State)>,
 }
 
-#[derive(Default)]
-struct Res {
-    stack: Vec<i64>,
-    err: Option<String>,
-    int_format: IntFormat,
+// how many commits can be undone
+const UNDO_SIZE: usize = 100;
+
+enum Mode {
+    Input,
+    // selecting a stack element, and editing it when `edit` is set
+    Stack { sel: usize, edit: Option<Input> },
+    // the .vars popup in full-screen mode, until a key is pressed
+    Vars,
+    // the .help popup in full-screen mode, scrolled down `scroll` lines
+    Help { scroll: usize },
+    // asking whether to commit a line that throws something away
+    Confirm(String),
 }
 
-impl Res {
-    fn render(&self) -> String {
-        let mut out = "".to_owned();
-        if self.int_format == IntFormat::Dec {
-            for &x in self.stack.iter() {
-                out.push_str(&format!("{} ", x));
-            }
-        } else {
-            for &x in self.stack.iter() {
-                out.push_str(&format!("{:#x} ", x));
-            }
-        }
+struct Args {
+    cmd: Cmd,
+    expr: String,
+    // replaces the configured sinks for this session when given
+    sinks: Option<Vec<Sink>>,
+    imports: Vec<Import>,
+    auto_session: bool,
+    history: bool,
+    full_screen: bool,
+    yes: bool,
+    sandbox: bool,
+    color: bool,
+    trace: bool,
+    accumulate: bool,
+}
 
-        out
-    }
+enum Cmd {
+    // evaluate the expression once, or start the TUI without one
+    Run,
+    // `c gen`, evaluate the expression `count` times
+    Gen { count: usize, seed: Option<u64>, format: GenFormat },
+    // `c --bench <expr> <runs>`
+    Bench(u32),
+    // `c --serve <socket>`, see server::serve
+    Serve(PathBuf),
+    // `c completions <shell>`
+    Completions(String),
+    // `c cheatsheet [format]`
+    Cheatsheet(String),
+    // `c tutor`, the TUI with lessons
+    Tutor,
 }
 
+#[derive(Clone, Copy)]
+enum GenFormat {
+    Plain,
+    Csv,
+    Json,
+}
 
-fn parse(inp: &str) -> Res {
-    let mut stack = vec![];
-    let mut err = None;
-    let mut int_format = IntFormat::Dec;
+fn parse_args() -> Result<Args, String> {
+    let mut expr = vec![];
+    let mut sinks = None;
+    let mut imports = vec![];
+    let mut auto_session = false;
+    let mut history = true;
+    let mut full_screen = false;
+    let mut yes = false;
+    let mut sandbox = false;
+    let mut color = true;
+    let mut trace = false;
+    let mut accumulate = false;
 
-    for x in inp.split_whitespace() {
-        if !x.is_ascii() {
-            // handle later
-            continue;
-        }
+    let mut args = std::env::args().skip(1).peekable();
+    let mut cmd = match args.peek().map(String::as_str) {
+        Some("gen") => {
+            args.next();
+            Cmd::Gen { count: 1, seed: None, format: GenFormat::Plain }
+        },
+        Some("completions") => {
+            args.next();
+            Cmd::Completions(args.next().ok_or("completions needs a shell: bash, zsh or fish")?)
+        },
+        Some("cheatsheet") => {
+            args.next();
+            Cmd::Cheatsheet(args.next().unwrap_or("text".into()))
+        },
+        Some("tutor") => {
+            args.next();
+            Cmd::Tutor
+        },
+        _ => Cmd::Run,
+    };
 
-        if let Some(x) = x.strip_prefix("0x") &&
-            let Ok(num) = i64::from_str_radix(x, 16) {
-            stack.push(num);
-            int_format = IntFormat::Hex;
-            continue;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), &mut cmd) {
+            ("--sink", _) => {
+                let spec = args.next().ok_or("--sink needs a value")?;
+                sinks.get_or_insert_with(Vec::new).push(spec.parse()?);
+            },
+            ("--no-sinks", _) => { sinks = Some(vec![]); },
+            ("--from-json", _) => {
+                let path = args.next().ok_or("--from-json needs a path")?;
+                imports.push(Import::Json { path: path.into(), pointer: "".into() });
+            },
+            ("--select", _) => {
+                let Some(Import::Json { pointer, .. }) = imports.last_mut() else {
+                    return Err("--select has to follow --from-json".into());
+                };
+                *pointer = args.next().ok_or("--select needs a JSON pointer")?;
+            },
+            ("--from-csv", _) => {
+                let path = args.next().ok_or("--from-csv needs a path")?;
+                imports.push(Import::Csv { path: path.into(), col: 1 });
+            },
+            ("--col", _) => {
+                let Some(Import::Csv { col, .. }) = imports.last_mut() else {
+                    return Err("--col has to follow --from-csv".into());
+                };
+                *col = args.next().and_then(|n| n.parse().ok()).filter(|&n| n > 0)
+                    .ok_or("--col needs a column number, counting from 1")?;
+            },
+            ("--auto-session", Cmd::Run) => { auto_session = true; },
+            ("--no-history", Cmd::Run) => { history = false; },
+            ("--full-screen", Cmd::Run | Cmd::Tutor) => { full_screen = true; },
+            ("--yes", Cmd::Run) => { yes = true; },
+            ("--sandbox", _) => { sandbox = true; },
+            ("--no-color", Cmd::Run | Cmd::Tutor) => { color = false; },
+            ("--trace", Cmd::Run) => { trace = true; },
+            ("--accumulate", Cmd::Run) => { accumulate = true; },
+            ("--bench", Cmd::Run) => {
+                let usage = "--bench needs an expression and a number of runs";
+                expr.push(args.next().ok_or(usage)?);
+                cmd = Cmd::Bench(args.next().and_then(|n| n.parse().ok()).filter(|&n| n > 0).ok_or(usage)?);
+            },
+            ("--serve", Cmd::Run) => {
+                cmd = Cmd::Serve(args.next().ok_or("--serve needs a socket path")?.into());
+            },
+            ("--count", Cmd::Gen { count, .. }) => {
+                *count = args.next().and_then(|n| n.parse().ok()).ok_or("--count needs a number")?;
+            },
+            ("--seed", Cmd::Gen { seed, .. }) => {
+                *seed = Some(args.next().and_then(|n| n.parse().ok()).ok_or("--seed needs a number")?);
+            },
+            ("--format", Cmd::Gen { format, .. }) => {
+                *format = match args.next().as_deref() {
+                    Some("plain") => GenFormat::Plain,
+                    Some("csv") => GenFormat::Csv,
+                    Some("json") => GenFormat::Json,
+                    _ => return Err("--format needs one of plain, csv or json".into()),
+                };
+            },
+            ("--", _) => { expr.extend(args.by_ref()); },
+            _ => expr.push(arg),
         }
+    }
 
-        if let Ok(num) = x.parse() {
-            stack.push(num);
-            continue;
-        }
+    Ok(Args {
+        cmd,
+        expr: expr.join(" "),
+        sinks,
+        imports,
+        auto_session,
+        history,
+        full_screen,
+        yes,
+        sandbox,
+        color,
+        trace,
+        accumulate,
+    })
+}
 
-        if x.len() < 1 {
-            continue;
+/// `c gen`: print the stack of `count` evaluations, one per line. The
+/// random number generator carries over between them, so a seed makes the
+/// whole output reproducible.
+fn gen(base: State, expr: &str, count: usize, seed: Option<u64>, format: GenFormat) -> Result<(), Box<dyn Error>> {
+    let mut rng = seed;
+    for _ in 0..count {
+        let mut state = State { rng, ..base.clone() };
+        eval(expr, &mut state).map_err(|e| e.to_string())?;
+        rng = state.rng;
+        if let Some(err) = session::run_effects(&mut state) {
+            eprintln!("{err}");
         }
 
-        let (head, rest) = x.split_at(1);
-
-        match head {
-            // iota, ( n --- 1 .. n )
-            "i" => {
-                if let Some(count) = stack.pop() {
-                    stack.extend(1..=count);
-                } else {
-                    err = Some("i needs a number".into());
-                }
-                continue;
-            },
-            // fold, /op, ( a b .. x --- a op b op .. op x )
-            "/" => {
-                if let Some(op) = operator(rest) {
-                    let res = stack.iter().copied().reduce(op).unwrap();
-                    stack.truncate(0);
-                    stack.push(res);
-                } else {
-                    err = Some("/<op>".into())
-                }
-                continue;
-
-            }
-            "." => {
-                match rest {
-                    "h" => { int_format = IntFormat::Hex; },
-                    "d" => { int_format = IntFormat::Dec; },
-                    _ => {},
-                }
-                continue;
+        let line = match format {
+            GenFormat::Plain => state.render().trim_end().to_owned(),
+            GenFormat::Csv => {
+                state.stack.iter().map(|&x| state.render_value(x)).collect::<Vec<_>>().join(",")
             },
-            _ => {},
-        }
+            GenFormat::Json => {
+                let values = state.stack.iter().map(|&x| server::value_json(x));
+                serde_json::Value::Array(values.collect()).to_string()
+            },
+        };
+        println!("{line}");
+    }
 
-        if let Some(op) = operator(head) &&
-            let Some(a) = stack.pop() &&
-            let Some(b) = stack.pop() {
+    Ok(())
+}
 
-            stack.push(op(b,a));
+/// `c --bench`: evaluate the expression `runs` times, each from the same
+/// state, and print the stack it leaves and how long an evaluation took.
+fn bench(base: State, expr: &str, runs: u32) -> Result<(), Box<dyn Error>> {
+    let mut times = vec![];
+    let mut state = base.clone();
+    for _ in 0..runs {
+        state = base.clone();
+        let start = Instant::now();
+        let result = eval(expr, &mut state);
+        times.push(start.elapsed());
+        result.map_err(|e| e.to_string())?;
+    }
 
-            continue;
-        }
+    println!("{}", state.render().trim_end());
+    let min = times.iter().min().copied().unwrap_or_default();
+    let mean = times.iter().sum::<Duration>() / runs;
+    println!("{runs} runs, min {}, mean {}", duration(min), duration(mean));
+    Ok(())
+}
 
-        err = Some(format!("couldn't parse '{x}'"));
+/// To three digits or so, like `840µs` or `1.25s`.
+fn duration(took: Duration) -> String {
+    let secs = took.as_secs_f64();
+    let (x, unit) = match secs {
+        _ if secs >= 1.0 => (secs, "s"),
+        _ if secs >= 1e-3 => (secs * 1e3, "ms"),
+        _ if secs >= 1e-6 => (secs * 1e6, "µs"),
+        _ => (secs * 1e9, "ns"),
+    };
+    let decimals = if x >= 100.0 { 0 } else if x >= 10.0 { 1 } else { 2 };
+    format!("{x:.decimals$}{unit}")
+}
 
+/// Send a committed result to every sink, returning the first failure.
+fn send(sinks: &[Sink], result: &str) -> Option<String> {
+    let result = result.trim_end();
+    if result.is_empty() {
+        return None;
     }
 
-    Res {
-        stack,
-        err,
-        int_format,
+    let mut err = None;
+    for sink in sinks {
+        if let Err(e) = sink.send(result) {
+            err.get_or_insert(format!("sink: {e}"));
+        }
     }
+    err
 }
 
-struct S {
-    input: tui_input::Input,
-    output: Res,
+/// Used as the [`State::clock`].
+fn unix_time() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
-    if !args.is_empty() {
-        println!("{}", parse(&args).render());
+    let args = parse_args()?;
+    let config = Config::load()?;
+    // the tutor keeps what's typed to itself
+    let tutor = matches!(args.cmd, Cmd::Tutor);
+    let sinks = if tutor { vec![] } else { args.sinks.unwrap_or(config.sinks) };
+
+    let mut base = State {
+        loader: Some(session::load),
+        clock: Some(unix_time),
+        top_first: config.top_first,
+        sandbox: args.sandbox || config.sandbox,
+        trace: args.trace,
+        constants: config.constants.into_iter().collect(),
+        ..State::default()
+    };
+    // a restored stack goes on from line to line, as when accumulating
+    let restored = args.auto_session && session::restore_auto(&mut base)? && !base.stack.is_empty();
+    for import in &args.imports {
+        base.stack.extend(import.read()?);
+    }
+
+    match args.cmd {
+        Cmd::Run | Cmd::Tutor => {},
+        Cmd::Completions(shell) => {
+            print!("{}", completions::script(&shell, &base.constants)?);
+            return Ok(());
+        },
+        Cmd::Cheatsheet(format) => {
+            print!("{}", cheatsheet::render(&format)?);
+            return Ok(());
+        },
+        Cmd::Gen { count, seed, format } => return gen(base, &args.expr, count, seed, format),
+        Cmd::Bench(runs) => return bench(base, &args.expr, runs),
+        Cmd::Serve(path) => return Ok(server::serve(&path, base, &sinks).map_err(|e| e.to_string())?),
+    }
+
+    if !args.expr.is_empty() && !tutor {
+        let mut state = base;
+        let start = Instant::now();
+        if let Err(e) = eval(&args.expr, &mut state) {
+            eprintln!("{e}");
+        }
+        if state.timed {
+            eprintln!("took {}", duration(start.elapsed()));
+        }
+        if let Some(warnings) = warnings(&state) {
+            eprintln!("{warnings}");
+        }
+        for line in trace(&state) {
+            eprintln!("{line}");
+        }
+        let out = state.render();
+        println!("{}", out);
+        if let Some(ruler) = bit_ruler(&state, out.trim_end().chars().count()) {
+            println!("{ruler}");
+        }
+        if state.effects.contains(&Effect::ShowVars) {
+            println!("{}", state.vars_listing().join("\n"));
+        }
+        if state.effects.contains(&Effect::ShowHelp) {
+            println!("{}", help_lines(&state).join("\n"));
+        }
+        let saved = session::run_effects(&mut state);
+        if let Some(err) = send(&sinks, &out).or(saved) {
+            eprintln!("{err}");
+        }
+        if args.auto_session {
+            session::save(session::AUTO, &state.to_input())?;
+        }
         return Ok(());
     }
 
     crossterm::terminal::enable_raw_mode()?;
 
-    let stdout = std::io::stdout();
+    let mut stdout = std::io::stdout();
+    crossterm::execute!(stdout, crossterm::event::EnableBracketedPaste)?;
+    if args.full_screen {
+        // inline, the mouse is left to the terminal for its scrollback
+        crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen, crossterm::event::EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::with_options(
         backend,
         TerminalOptions {
-            viewport: Viewport::Inline(3),
+            viewport: if args.full_screen { Viewport::Fullscreen } else { Viewport::Inline(config.height) },
         })?;
 
     let state = S {
         input: Default::default(),
-        output: Default::default(),
+        output: base.clone(),
+        evaluator: Evaluator::new(),
+        cache: Cache::default(),
+        recall: None,
+        history: History::load(if args.history && !tutor { config.history_size } else { 0 })?,
+        committed: base.clone(),
+        auto_session: args.auto_session,
+        base,
+        err: None,
+        sinks,
+        msg: None,
+        took: Duration::ZERO,
+        mode: Mode::Input,
+        log: args.full_screen.then(Vec::new),
+        scroll: 0,
+        confirm: config.confirm && !args.yes,
+        accumulate: args.accumulate || config.accumulate || restored,
+        snippets: snippets::load()?,
+        height: config.height,
+        drawn: RefCell::new(vec![]),
+        input_at: Cell::new((Rect::default(), 0)),
+        completing: None,
+        tutor: tutor.then_some(0),
+        keys: config.keys,
+        undo: vec![],
+        redo: vec![],
+        theme: if args.color && !theme::no_color_env() { config.theme } else { Theme::mono() },
     };
 
-    run_app(&mut terminal, state)?;
+    let state = run_app(&mut terminal, state)?;
 
     crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), crossterm::event::DisableBracketedPaste)?;
+    if args.full_screen {
+        crossterm::execute!(std::io::stdout(), crossterm::event::DisableMouseCapture, crossterm::terminal::LeaveAlternateScreen)?;
+    }
+
+    if state.auto_session {
+        session::save(session::AUTO, &state.committed.to_input())?;
+    }
 
     Ok(())
 }
 
-fn run_app<B: Backend>(term: &mut Terminal<B>, mut s: S) -> Result<(), Box<dyn Error>> {
+fn run_app<B: Backend>(term: &mut Terminal<B>, mut s: S) -> Result<S, Box<dyn Error>> {
+    if let Some(lesson) = s.tutor {
+        show_lines(term, &mut s, tutor::LESSONS[lesson].text)?;
+    }
     loop {
         term.draw(|frame| ui(frame, &s))?;
 
+        if s.evaluator.pending() && !crossterm::event::poll(Duration::from_millis(20))? {
+            if let Some((output, err, took)) = s.evaluator.poll() {
+                s.output = output;
+                s.err = err;
+                s.took = took;
+            }
+            continue;
+        }
+
         match crossterm::event::read()? {
             ref ev@Event::Key(key) => {
-                if key.code == KeyCode::Char('d') && key.modifiers == KeyModifiers::CONTROL {
-                    // exit on C-d
-                    return Ok(())
-                } else if key.code == KeyCode::Enter {
-                    term.insert_before(1, |buf| {
-                        Paragraph::new(s.output.render()).render(buf.area, buf);
-                    })?;
-                    s.input.reset();
+                s.msg = None;
+                let completing = s.completing.take();
+                let action = s.keys.action(key);
+                // typing can go on while a long input is evaluated, anything
+                // else needs its output
+                let typing = matches!(s.mode, Mode::Input) && key.modifiers != KeyModifiers::CONTROL && action.is_none() &&
+                    matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete | KeyCode::Left | KeyCode::Right);
+                if !typing {
+                    settle(&mut s);
+                }
+                // Esc still closes whatever is open, even bound to quit
+                let closing = key.code == KeyCode::Esc && !matches!(s.mode, Mode::Input);
+                if action == Some(Action::Quit) && !closing {
+                    return Ok(s)
+                } else if action == Some(Action::Copy) {
+                    // copy the result, or the selected value in stack mode
+                    let text = match s.mode {
+                        Mode::Stack { sel, .. } => s.output.render_value(s.output.stack[sel]),
+                        _ => s.output.render().trim_end().to_owned(),
+                    };
+                    s.msg = Some(match clipboard::copy(&text) {
+                        Ok(()) => format!("copied {text}"),
+                        Err(e) => format!("copy: {e}"),
+                    });
+                } else if let Mode::Confirm(_) = s.mode {
+                    // a question takes the next key, whatever it is bound to
+                    s.mode = Mode::Input;
+                    if key.code == KeyCode::Char('y') {
+                        commit(term, &mut s)?;
+                    }
+                } else if let Mode::Stack { .. } = s.mode {
+                    stack_key(&mut s, key);
+                } else if let Mode::Vars = s.mode {
+                    s.mode = Mode::Input;
+                } else if let Mode::Help { scroll } = &mut s.mode {
+                    // the popup fills the log pane, less its border
+                    let page = (term.size()?.height as usize).saturating_sub(s.height as usize + 2).max(1);
+                    let last = help_lines(&s.base).len().saturating_sub(page);
+                    match key.code {
+                        KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                        KeyCode::Down => *scroll = (*scroll + 1).min(last),
+                        KeyCode::PageUp => *scroll = scroll.saturating_sub(page),
+                        KeyCode::PageDown => *scroll = (*scroll + page).min(last),
+                        _ => s.mode = Mode::Input,
+                    }
+                } else if matches!(action, Some(Action::Undo | Action::Redo)) {
+                    undo(&mut s, action == Some(Action::Redo));
+                } else if action == Some(Action::Help) {
+                    show_help(term, &mut s)?;
+                } else if action == Some(Action::NextStack) {
+                    next_stack(&mut s);
+                } else if key.code == KeyCode::Char('s') && key.modifiers == KeyModifiers::CONTROL {
+                    // C-s edits the stack directly
+                    if s.err.is_some() {
+                        s.msg = Some("fix the input before editing the stack".into());
+                    } else if s.output.stack.is_empty() {
+                        s.msg = Some("the stack is empty".into());
+                    } else {
+                        s.mode = Mode::Stack { sel: s.output.stack.len() - 1, edit: None };
+                    }
+                } else if matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+                    complete(&mut s, completing, key.code == KeyCode::BackTab);
+                } else if action == Some(Action::Commit) && snippet(&mut s) {
+                    // the line was a snippet command, not something to commit
+                } else if action == Some(Action::Commit) {
+                    match confirmation(&s) {
+                        Some(question) => { s.mode = Mode::Confirm(question); },
+                        None => commit(term, &mut s)?,
+                    }
+                } else if s.log.is_some() && matches!(key.code, KeyCode::PageUp | KeyCode::PageDown) {
+                    // a page is the log pane, what is left above the input rows
+                    let page = (term.size()?.height as usize).saturating_sub(s.height as usize).max(1);
+                    scroll_log(&mut s, page, key.code == KeyCode::PageUp, page);
+                } else if matches!(action, Some(Action::HistoryPrev | Action::HistoryNext)) {
+                    recall(&mut s, action == Some(Action::HistoryPrev));
                 } else {
                     s.input.handle_event(ev);
                 }
             },
             // Event::FocusGained => todo!(),
             // Event::FocusLost => todo!(),
-            // Event::Mouse(_) => todo!(),
-            // Event::Paste(_) => todo!(),
-            // Event::Resize(_, _) => todo!(),
+            Event::Mouse(MouseEvent { kind, column, row, .. }) => {
+                let page = (term.size()?.height as usize).saturating_sub(s.height as usize).max(1);
+                match kind {
+                    MouseEventKind::ScrollUp => scroll_log(&mut s, page, true, 3),
+                    MouseEventKind::ScrollDown => scroll_log(&mut s, page, false, 3),
+                    MouseEventKind::Down(MouseButton::Left) if matches!(s.mode, Mode::Input) => click(&mut s, column, row),
+                    _ => {},
+                }
+            },
+            Event::Paste(text) => {
+                s.msg = None;
+                match &mut s.mode {
+                    Mode::Input => paste(&mut s.input, &text),
+                    Mode::Stack { edit: Some(edit), .. } => paste(edit, &text),
+                    _ => {},
+                }
+            },
+            Event::Resize(width, _) => resize(term, &s, width)?,
             _ => {},
         }
 
-        s.output = parse(s.input.value());
+        if s.input.value().len() > background::LONG_INPUT {
+            s.evaluator.start(s.input.value().to_owned(), s.base.clone());
+        } else {
+            s.evaluator.cancel();
+            (s.output, s.err, s.took) = s.cache.eval(s.input.value(), &s.base);
+        }
 
     }
 }
 
-fn ui(f: &mut Frame, s: &S) {
+fn help_lines(state: &State) -> Vec<String> {
+    let mut lines = cheatsheet::text().lines().map(str::to_owned).collect::<Vec<_>>();
+    if !state.constants.is_empty() {
+        lines.push("Constants from the config".into());
+        let width = state.constants.keys().map(String::len).max().unwrap_or(0);
+        lines.extend(state.constants.iter().map(|(name, &x)| format!("  {name:width$}  {}", state.render_value(x))));
+        lines.push(String::new());
+    }
+    lines
+}
 
-    let chunks = Layout::default()
-        .constraints([Constraint::Max(1), Constraint::Max(1), Constraint::Max(1)])
-        .split(f.size());
+/// F1: the help popup in full-screen mode, otherwise the listing goes to
+/// the scrollback above the prompt.
+fn show_help<B: Backend>(term: &mut Terminal<B>, s: &mut S) -> std::io::Result<()> {
+    if s.log.is_some() {
+        s.mode = Mode::Help { scroll: 0 };
+        return Ok(());
+    }
+    let lines = help_lines(&s.base);
+    show_lines(term, s, &lines.iter().map(String::as_str).collect::<Vec<_>>())
+}
 
-    // error message
-    if let Some(ref err) = s.output.err {
-        let error = Paragraph::new(err.clone());
-        f.render_widget(error, chunks[0]);
+/// Scroll the full-screen log `lines` back, or forward, keeping a page of
+/// it in view.
+fn scroll_log(s: &mut S, page: usize, back: bool, lines: usize) {
+    let top = s.log.as_ref().map_or(0, |log| log.len().saturating_sub(page));
+    s.scroll = if back {
+        (s.scroll + lines).min(top)
+    } else {
+        s.scroll.saturating_sub(lines)
+    };
+}
+
+/// Move the cursor to the character clicked on, or the end of the input
+/// when the click is past it.
+fn click(s: &mut S, column: u16, row: u16) {
+    let (area, start) = s.input_at.get();
+    if row != area.y || column < area.x || column >= area.x + area.width {
+        return;
+    }
+    let mut x = (column - area.x) as usize;
+    let mut cursor = start;
+    for c in s.input.value().chars().skip(start) {
+        let width = c.width().unwrap_or(0);
+        if x < width {
+            break;
+        }
+        x -= width;
+        cursor += 1;
+    }
+    s.input = std::mem::take(&mut s.input).with_cursor(cursor);
+}
+
+/// Insert pasted text at the cursor in one go. Line breaks and tabs, as in
+/// a column copied from a spreadsheet, separate words like spaces do.
+fn paste(input: &mut Input, text: &str) {
+    let text = text.replace("\r\n", " ").replace(['\r', '\n', '\t'], " ");
+    let cursor = input.cursor();
+    let value = input.value();
+    let at = value.char_indices().nth(cursor).map_or(value.len(), |(i, _)| i);
+    let value = format!("{}{text}{}", &value[..at], &value[at..]);
+    *input = Input::new(value).with_cursor(cursor + text.chars().count());
+}
+
+/// The terminal re-wraps the rows drawn at the old width, so the inline
+/// viewport can end up taller and further up than ratatui thinks. Clear
+/// from where the re-wrapped rows start and put the viewport there.
+fn resize<B: Backend>(term: &mut Terminal<B>, s: &S, width: u16) -> std::io::Result<()> {
+    if s.log.is_some() {
+        // the full screen just gets drawn again
+        return term.autoresize();
     }
 
-    // current output
-    let output = Paragraph::new(s.output.render());
-    f.render_widget(output, chunks[1]);
+    let width = width.max(1) as usize;
+    let rows = s.drawn.borrow().iter().map(|used| used.max(&1).div_ceil(width) as u16).collect::<Vec<_>>();
+    // the cursor stays at the end of the prompt row, wherever that went
+    let (_, cursor) = crossterm::cursor::position()?;
+    let top = cursor.saturating_sub(rows.iter().sum::<u16>().saturating_sub(1));
+    crossterm::execute!(
+        std::io::stdout(),
+        crossterm::cursor::MoveTo(0, top),
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown),
+        // the prompt row, which the viewport is placed around
+        crossterm::cursor::MoveTo(0, top + rows.len().saturating_sub(1) as u16),
+    )?;
+    term.resize(term.size()?)
+}
+
+/// `line` cut to `width` columns, keeping the end where the top of the
+/// stack is.
+fn fit(line: &str, width: usize) -> String {
+    let len = line.chars().count();
+    if len <= width {
+        return line.to_owned();
+    }
+    let tail = line.chars().skip(len - width.saturating_sub(1)).collect::<String>();
+    format!("…{tail}")
+}
 
+/// Finish evaluating the input in the foreground if it is still being
+/// evaluated in the background.
+fn settle(s: &mut S) {
+    if s.evaluator.pending() {
+        s.evaluator.cancel();
+        s.output = s.base.clone();
+        s.err = eval(s.input.value(), &mut s.output).err();
+    }
+}
+
+/// A `.snip <save|insert> <name>` in `text`: whether it saves, the name
+/// and the part of `text` it takes up.
+fn snip_command(text: &str) -> Option<(bool, &str, std::ops::Range<usize>)> {
+    let spans = c::tokens(text, &State::default()).into_iter().map(|token| token.span).collect::<Vec<_>>();
+    let i = spans.iter().position(|span| &text[span.clone()] == ".snip")?;
+    let [action, name] = [spans.get(i + 1)?, spans.get(i + 2)?].map(|span| &text[span.clone()]);
+    let save = match action {
+        "save" => true,
+        "insert" => false,
+        _ => return None,
+    };
+    Some((save, name, spans[i].start..spans[i + 2].end))
+}
+
+/// Enter on a line with `.snip save <name>` saves the rest of the line,
+/// `.snip insert <name>` is replaced by the snippet. This goes by the text,
+/// a snippet like `1.08 m` doesn't evaluate on its own. Returns whether the
+/// line had either.
+fn snippet(s: &mut S) -> bool {
+    let text = s.input.value();
+    // sandboxed, `.snip` is an error like any other
+    let Some((save, query, span)) = snip_command(text).filter(|_| !s.base.sandbox) else {
+        return false;
+    };
+    let (before, after) = (text[..span.start].trim_end(), text[span.end..].trim_start());
+
+    if save {
+        let expr = [before, after].join(" ").trim().to_owned();
+        s.msg = Some(if expr.is_empty() {
+            "there is nothing to save next to .snip save".into()
+        } else {
+            match snippets::save(&mut s.snippets, query, &expr) {
+                Ok(()) => format!("saved snippet '{query}'"),
+                Err(e) => e,
+            }
+        });
+        s.input = Input::new(expr);
+    } else {
+        let Some(name) = snippets::find(&s.snippets, query).first().copied() else {
+            s.msg = Some(format!("no snippet matches '{query}'"));
+            return true;
+        };
+        let expr = &s.snippets[name];
+        let before = if before.is_empty() { String::new() } else { format!("{before} ") };
+        let cursor = before.chars().count() + expr.chars().count();
+        let after = if after.is_empty() { String::new() } else { format!(" {after}") };
+        s.input = Input::new(format!("{before}{expr}{after}")).with_cursor(cursor);
+    }
+    true
+}
+
+/// Tab: complete the word before the cursor, or go on to the next (or
+/// with `back` the previous) of the candidates the last Tab found.
+fn complete(s: &mut S, completing: Option<(Vec<String>, usize, std::ops::Range<usize>)>, back: bool) {
+    let (candidates, i, span) = match completing {
+        Some((candidates, i, span)) => {
+            let n = candidates.len();
+            let i = if back { (i + n - 1) % n } else { (i + 1) % n };
+            (candidates, i, span)
+        },
+        None => {
+            let text = s.input.value();
+            let at = text.char_indices().nth(s.input.cursor()).map_or(text.len(), |(i, _)| i);
+            let span = c::tokens(text, &s.output).into_iter().map(|token| token.span).find(|w| w.start <= at && at <= w.end).unwrap_or(at..at);
+            let prefix = &text[span.start..at];
+            // the output has the variables set earlier in the line too
+            let candidates = c::complete(prefix, &s.output);
+            if candidates.is_empty() {
+                s.msg = Some(format!("nothing starts with '{prefix}'"));
+                return;
+            }
+            let i = if back { candidates.len() - 1 } else { 0 };
+            (candidates, i, span)
+        },
+    };
+
+    let text = s.input.value();
+    let value = format!("{}{}{}", &text[..span.start], candidates[i], &text[span.end..]);
+    let cursor = text[..span.start].chars().count() + candidates[i].chars().count();
+    let span = span.start..span.start + candidates[i].len();
+    s.input = Input::new(value).with_cursor(cursor);
+    if candidates.len() > 1 {
+        s.completing = Some((candidates, i, span));
+    }
+}
+
+/// With `.trace`, a line for each word of an evaluation, with the stack
+/// after it.
+fn trace(state: &State) -> Vec<String> {
+    let steps = state.effects.iter().filter_map(|effect| match effect {
+        Effect::Trace(word, stack) => Some((word, stack)),
+        _ => None,
+    }).collect::<Vec<_>>();
+    let width = steps.iter().map(|(word, _)| word.width()).max().unwrap_or(0);
+    steps.into_iter().map(|(word, stack)| format!("  {word}{:1$}  {stack}", "", width - word.width())).collect()
+}
+
+/// The warnings of an evaluation, in one line.
+fn warnings(state: &State) -> Option<String> {
+    let warnings = state.effects.iter().filter_map(|effect| match effect {
+        Effect::Warn(warning) => Some(warning.as_str()),
+        _ => None,
+    }).collect::<Vec<_>>();
+    (!warnings.is_empty()).then(|| warnings.join("; "))
+}
+
+/// What committing the input would throw away, as a question to confirm.
+fn confirmation(s: &S) -> Option<String> {
+    if !s.confirm {
+        return None;
+    }
+    let mut lost = vec![];
+    for effect in &s.output.effects {
+        match effect {
+            Effect::Discard(what) => lost.push(what.clone()),
+            Effect::Save { name, .. } if session::exists(name) => lost.push(format!("session '{name}'")),
+            _ => {},
+        }
+    }
+    (!lost.is_empty()).then(|| format!("this throws away {}, commit anyway? y/n", lost.join(" and ")))
+}
+
+/// Enter: show the result, carry out its effects and start a new line.
+fn commit<B: Backend>(term: &mut Terminal<B>, s: &mut S) -> std::io::Result<()> {
+    let out = s.output.render();
+    // what @n refers to this result as
+    let label = format!("@{}  ", s.base.results.len() + 1);
+    let shown = format!("{label}{out}");
+    let vars = s.output.effects.contains(&Effect::ShowVars).then(|| s.output.vars_listing());
+    let help = s.output.effects.contains(&Effect::ShowHelp);
+    let trace = trace(&s.output);
+    if let Some(log) = &mut s.log {
+        log.push(format!("> {}", s.input.value()));
+        log.extend(trace);
+        log.push(shown);
+        s.scroll = 0;
+        if vars.is_some() {
+            s.mode = Mode::Vars;
+        } else if help {
+            s.mode = Mode::Help { scroll: 0 };
+        }
+    } else {
+        // without room for a popup the listings go above the result
+        let help = help.then(|| help_lines(&s.base)).unwrap_or_default();
+        let mut lines = trace.into_iter().chain(vars.unwrap_or_default()).chain(help).map(Line::from).collect::<Vec<_>>();
+        lines.push(Line::from(vec![Span::raw(label), Span::styled(out.clone(), s.theme.result)]));
+        term.insert_before(lines.len() as u16, |buf| {
+            Paragraph::new(lines).render(buf.area, buf);
+        })?;
+    }
+    let warned = warnings(&s.output);
+    let loaded = s.output.effects.contains(&Effect::Loaded);
+    let saved = session::run_effects(&mut s.output);
+    s.msg = send(&s.sinks, &out).or(saved).or(warned);
+    if let Err(e) = s.history.push(s.input.value()) {
+        s.msg.get_or_insert(format!("history: {e}"));
+    }
+    s.recall = None;
+    if s.undo.len() == UNDO_SIZE {
+        s.undo.remove(0);
+    }
+    s.undo.push((s.base.clone(), s.committed.clone()));
+    s.redo.clear();
+    s.cache.clear();
+    // variables, results, the random numbers and .time are kept for the
+    // following lines, and the stack too when accumulating or after
+    // switching to another one
+    let switched = s.output.workspace != s.base.workspace;
+    s.base.vars = s.output.vars.clone();
+    s.base.rng = s.output.rng;
+    s.base.timed = s.output.timed;
+    s.base.results.push(s.output.stack.clone());
+    s.base.workspace = s.output.workspace.clone();
+    s.base.workspaces = s.output.workspaces.clone();
+    if s.accumulate || switched {
+        s.base.stack = s.output.stack.clone();
+    }
+    if switched {
+        s.base.int_format = s.output.int_format;
+    }
+    if loaded {
+        // the following lines go on from the session, its settings and its
+        // stack as when accumulating
+        s.accumulate = true;
+        s.base = State { results: std::mem::take(&mut s.base.results), ..s.output.clone() };
+    }
+    s.committed = s.output.clone();
+    s.input.reset();
+
+    if let Some(lesson) = s.tutor {
+        next_lesson(term, s, lesson, &out)?;
+    }
+
+    Ok(())
+}
+
+/// `c tutor`: go on to the next lesson once the output is what this one
+/// asks for.
+fn next_lesson<B: Backend>(term: &mut Terminal<B>, s: &mut S, lesson: usize, out: &str) -> std::io::Result<()> {
+    if tutor::LESSONS[lesson].expect.is_some_and(|expect| expect != out.trim_end()) {
+        s.msg.get_or_insert("not quite, try again".into());
+        return Ok(());
+    }
+    s.tutor = Some(lesson + 1).filter(|&next| next < tutor::LESSONS.len());
+    match s.tutor {
+        Some(next) => show_lines(term, s, tutor::LESSONS[next].text),
+        None => {
+            s.msg = Some("that's the tutor done, C-d quits".into());
+            Ok(())
+        },
+    }
+}
+
+/// Some lines above the prompt: at the end of the log in full-screen
+/// mode, otherwise in the scrollback.
+fn show_lines<B: Backend>(term: &mut Terminal<B>, s: &mut S, lines: &[&str]) -> std::io::Result<()> {
+    if let Some(log) = &mut s.log {
+        log.extend(lines.iter().map(|&line| line.to_owned()));
+        s.scroll = 0;
+        return Ok(());
+    }
+    let lines = lines.iter().map(|&line| Line::from(line)).collect::<Vec<_>>();
+    term.insert_before(lines.len() as u16, |buf| {
+        Paragraph::new(lines).render(buf.area, buf);
+    })
+}
+
+/// C-z: go back to before the last commit, or with `redo` forward again.
+/// What the commit sent to sinks or saved stays done.
+fn undo(s: &mut S, redo: bool) {
+    let (from, to) = if redo { (&mut s.redo, &mut s.undo) } else { (&mut s.undo, &mut s.redo) };
+    let Some((base, committed)) = from.pop() else {
+        s.msg = Some(format!("nothing to {}", if redo { "redo" } else { "undo" }));
+        return;
+    };
+    let n = s.base.results.len().max(base.results.len());
+    to.push((std::mem::replace(&mut s.base, base), std::mem::replace(&mut s.committed, committed)));
+    s.cache.clear();
+    s.msg = Some(format!("{} @{n}", if redo { "redid" } else { "undid" }));
+}
+
+/// C-n: switch to the next of the stacks made with `.stack <name>`, in
+/// order of their names.
+fn next_stack(s: &mut S) {
+    let mut names = s.base.workspaces.keys().map(String::as_str).collect::<Vec<_>>();
+    names.push(s.base.workspace_name());
+    names.sort_unstable();
+    if names.len() == 1 {
+        s.msg = Some("there is only the main stack, `.stack <name>` makes another".into());
+        return;
+    }
+    let at = names.iter().position(|&name| name == s.base.workspace_name()).unwrap();
+    let next = names[(at + 1) % names.len()].to_owned();
+    eval(&format!(".stack {next}"), &mut s.base).expect("switching stacks");
+    s.cache.clear();
+    s.msg = Some(format!("stack {next}"));
+}
+
+/// Up and Down: step through the history. A recalled line can be edited
+/// like any other, stepping past the newest line brings back what was
+/// typed before.
+fn recall(s: &mut S, up: bool) {
+    let lines = &s.history.lines;
+    let recall = match (s.recall.take(), up) {
+        (None, true) if !lines.is_empty() => Some((lines.len() - 1, s.input.value().to_owned())),
+        (Some((i, typed)), true) => Some((i.saturating_sub(1), typed)),
+        (Some((i, typed)), false) if i + 1 < lines.len() => Some((i + 1, typed)),
+        (Some((_, typed)), false) => {
+            s.input = Input::new(typed);
+            None
+        },
+        (None, _) => None,
+    };
+    if let Some((i, _)) = &recall {
+        s.input = Input::new(lines[*i].clone());
+    }
+    s.recall = recall;
+}
+
+/// Handle a key in stack mode. Changes to the stack are written back to
+/// the input line, so it always reproduces what is shown.
+fn stack_key(s: &mut S, key: KeyEvent) {
+    let Mode::Stack { sel, edit } = &mut s.mode else {
+        return;
+    };
+    let mut state = s.output.clone();
+    let len = state.stack.len();
+
+    // keys move through the stack as it is shown, a value per row too
+    let key = match key.code {
+        KeyCode::Up if edit.is_none() => KeyEvent { code: KeyCode::Left, ..key },
+        KeyCode::Down if edit.is_none() => KeyEvent { code: KeyCode::Right, ..key },
+        _ => key,
+    };
+    let key = match key.code {
+        KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l' | 'H' | 'L') if state.top_first && edit.is_none() => {
+            let code = match key.code {
+                KeyCode::Left => KeyCode::Right,
+                KeyCode::Right => KeyCode::Left,
+                KeyCode::Char('h') => KeyCode::Char('l'),
+                KeyCode::Char('l') => KeyCode::Char('h'),
+                KeyCode::Char('H') => KeyCode::Char('L'),
+                _ => KeyCode::Char('H'),
+            };
+            KeyEvent { code, ..key }
+        },
+        _ => key,
+    };
+
+    if let Some(input) = edit {
+        match key.code {
+            KeyCode::Esc => { *edit = None; },
+            KeyCode::Enter => {
+                // with the variables, results and settings of the line, in
+                // its word size
+                let mut value = State { stack: vec![], effects: vec![], ..state.clone() };
+                match eval(input.value(), &mut value) {
+                    Ok(stack) if stack.len() == 1 => {
+                        state.stack[*sel] = state.wrap(stack[0]);
+                        *edit = None;
+                    },
+                    Ok(_) => {
+                        s.msg = Some("an edit has to leave exactly one value".into());
+                    },
+                    Err(e) => {
+                        s.msg = Some(e.to_string());
+                    },
+                }
+            },
+            _ => { input.handle_event(&Event::Key(key)); },
+        }
+    } else {
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                s.mode = Mode::Input;
+                return;
+            },
+            (KeyCode::Left, KeyModifiers::NONE) | (KeyCode::Char('h'), _) => {
+                *sel = sel.saturating_sub(1);
+            },
+            (KeyCode::Right, KeyModifiers::NONE) | (KeyCode::Char('l'), _) => {
+                *sel = (*sel + 1).min(len - 1);
+            },
+            (KeyCode::Left, KeyModifiers::SHIFT) | (KeyCode::Char('H'), _) if *sel > 0 => {
+                state.stack.swap(*sel, *sel - 1);
+                *sel -= 1;
+            },
+            (KeyCode::Right, KeyModifiers::SHIFT) | (KeyCode::Char('L'), _) if *sel + 1 < len => {
+                state.stack.swap(*sel, *sel + 1);
+                *sel += 1;
+            },
+            (KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x'), _) => {
+                state.stack.remove(*sel);
+                if *sel == len - 1 {
+                    *sel = sel.saturating_sub(1);
+                }
+            },
+            (KeyCode::Char('c'), _) => {
+                state.stack.insert(*sel + 1, state.stack[*sel]);
+                *sel += 1;
+            },
+            (KeyCode::Enter | KeyCode::Char('e'), _) => {
+                let value = State { stack: vec![state.stack[*sel]], ..State::default() };
+                *edit = Some(Input::new(value.to_input()));
+            },
+            _ => {},
+        }
+    }
+
+    if state.stack.is_empty() {
+        s.mode = Mode::Input;
+    }
+    // the input now rebuilds the whole stack, including what the base
+    // started with (imports, a restored session), and the variables and
+    // parked stacks
+    s.base.stack.clear();
+    s.base.vars.clear();
+    s.base.workspaces.clear();
+    s.cache.clear();
+    s.input = Input::new(state.to_input_from(&s.base));
+}
+
+fn ui(f: &mut Frame, s: &S) {
+
+    // full-screen mode keeps the log above the rows of the inline viewport
+    let area = match &s.log {
+        Some(log) => {
+            let chunks = Layout::default()
+                .constraints([Constraint::Min(1), Constraint::Length(s.height)])
+                .split(f.size());
+            let end = log.len() - s.scroll;
+            let start = end.saturating_sub(chunks[0].height as usize);
+            let lines = log[start..end].iter().map(|l| Line::from(l.as_str())).collect::<Vec<_>>();
+            f.render_widget(Paragraph::new(lines), chunks[0]);
+
+            match s.mode {
+                Mode::Vars => vars_popup(f, &s.committed, chunks[0]),
+                Mode::Help { scroll } => help_popup(f, scroll, &s.base, chunks[0]),
+                _ => {},
+            }
+            chunks[1]
+        },
+        None => f.size(),
+    };
+
+    let chunks = Layout::default()
+        .constraints([Constraint::Max(1), Constraint::Min(1), Constraint::Max(1)])
+        .split(area);
+    // with more than one row for the output the stack goes a value per row
+    let sel = match s.mode {
+        Mode::Stack { sel, .. } => Some(sel),
+        _ => None,
+    };
+    let rows = (chunks[1].height > 1).then(|| stack_rows(&s.output, sel, chunks[1].height as usize, chunks[1].width as usize));
+
+    // error message, or what the keys do in stack mode
+    let hint = match s.mode {
+        Mode::Stack { edit: Some(_), .. } => Some("Enter save  Esc cancel".to_owned()),
+        Mode::Stack { .. } => Some("←/→ ↑/↓ select  H/L move  x delete  c duplicate  e edit  C-y copy  Esc done".to_owned()),
+        Mode::Vars => Some("press any key to close".to_owned()),
+        Mode::Help { .. } => Some("↑/↓ PgUp/PgDn scroll  any other key closes".to_owned()),
+        Mode::Confirm(ref question) => Some(question.clone()),
+        // the snippets `.snip insert` would pick from
+        Mode::Input => match snip_command(s.input.value()) {
+            Some((false, query, _)) => Some(match &snippets::find(&s.snippets, query)[..] {
+                [] => format!("no snippet matches '{query}'"),
+                [best, rest @ ..] => format!("Enter inserts {best} = {}  {}", s.snippets[*best], rest.join(" ")),
+            }),
+            // a value per row has it next to the top
+            _ if rows.is_some() => None,
+            _ => bit_ruler(&s.output, s.output.render().trim_end().chars().count().min(chunks[1].width as usize)),
+        },
+    };
+    let width = chunks[0].width as usize;
+    let mut drawn = vec![0; area.height as usize];
+
+    // the prompt row ends in the display format, word size and stack
+    // depth, when there's room
+    let status = format!(" {}{}{}{} i{} depth {}", if s.output.timed { format!("{} ", duration(s.took)) } else { "".into() },
+        if s.accumulate { "acc " } else { "" },
+        s.output.workspace.as_ref().map(|name| format!("{name} ")).unwrap_or_default(), s.output.int_format.name(), s.output.bits(), s.output.stack.len());
+    let status_width = if width >= status.len() + 20 { status.len() as u16 } else { 0 };
     let input_chunks = Layout::default()
-        .constraints([Constraint::Length(2), Constraint::Min(1)])
+        .constraints([Constraint::Length(2), Constraint::Min(1), Constraint::Length(status_width)])
         .direction(Direction::Horizontal)
         .split(chunks[2]);
 
+    // the word that failed, to point out in the input
+    let text = s.input.value();
+    let err_span = match (&s.mode, &s.err) {
+        (Mode::Input, Some(err)) if !s.evaluator.pending() && text.len() <= background::LONG_INPUT => {
+            err.span().filter(|span| text.get(span.clone()).is_some())
+        },
+        _ => None,
+    };
+    // and the column it starts at, under the error row
+    let scroll = s.input.visual_scroll((input_chunks[1].width as usize).saturating_sub(1));
+    let caret = err_span.as_ref()
+        .map(|span| text[..span.start].width())
+        .filter(|&x| x >= scroll)
+        .map(|x| 2 + x - scroll)
+        .filter(|&x| x < width);
+
+    let pending = s.evaluator.pending().then(|| "evaluating…".to_owned());
+    let failed = pending.is_none() && s.err.is_some();
+    if let Some((candidates, i, _)) = &s.completing {
+        // the Tab candidates, the one in the input highlighted and in view
+        let spans = candidates.iter().enumerate().map(|(j, name)| {
+            let style = if j == *i { s.theme.highlight } else { Style::default() };
+            [Span::styled(name.as_str(), style), Span::raw(" ")]
+        }).collect::<Vec<_>>().concat();
+        let end = spans[..=2 * i].iter().map(Span::width).sum::<usize>();
+        let line = Line::from(spans);
+        drawn[0] = line.width().min(width);
+        f.render_widget(Paragraph::new(line).scroll((0, end.saturating_sub(width) as u16)), chunks[0]);
+    } else if let Some(err) = pending.or(s.err.as_ref().map(EvalError::to_string)).or(warnings(&s.output)).or(s.msg.clone()).or(hint) {
+        // the caret goes after the message if there's room, before it if not
+        let err = match caret {
+            Some(x) if x > err.width() => format!("{err}{:1$}↓", "", x - err.width()),
+            Some(x) => format!("{:x$}↓ {err}", ""),
+            None => err,
+        };
+        drawn[0] = err.chars().count().min(width);
+        let error = Paragraph::new(err).style(if failed { s.theme.error } else { Style::default() });
+        f.render_widget(error, chunks[0]);
+    }
+
+    // current output, with the selected element highlighted in stack mode
+    if let Some(rows) = rows {
+        // next to the prompt
+        let skip = chunks[1].height as usize - rows.len();
+        let lines = rows.into_iter().enumerate().map(|(i, (line, selected))| {
+            drawn[1 + skip + i] = line.chars().count();
+            Line::styled(line, if selected { s.theme.highlight } else { s.theme.result })
+        }).collect::<Vec<_>>();
+        let area = Rect { y: chunks[1].y + skip as u16, height: lines.len() as u16, ..chunks[1] };
+        f.render_widget(Paragraph::new(lines), area);
+    } else {
+        let output = match s.mode {
+            Mode::Stack { sel, .. } => {
+                let mut spans = s.output.stack.iter().enumerate().map(|(i, &x)| {
+                    let style = if i == sel { s.theme.highlight } else { s.theme.result };
+                    [Span::styled(s.output.render_value(x), style), Span::raw(" ")]
+                }).collect::<Vec<_>>();
+                // scroll the selected value into view
+                let shown = if s.output.top_first {
+                    spans.reverse();
+                    spans.len() - 1 - sel
+                } else {
+                    sel
+                };
+                let line = Line::from(spans.concat());
+                let end = line.spans[..=2 * shown].iter().map(Span::width).sum::<usize>();
+                drawn[1] = line.width().min(width);
+                Paragraph::new(line).scroll((0, end.saturating_sub(width) as u16))
+            },
+            Mode::Input | Mode::Vars | Mode::Help { .. } | Mode::Confirm(_) => {
+                let line = fit(s.output.render().trim_end(), width);
+                drawn[1] = line.chars().count();
+                Paragraph::new(line).style(s.theme.result)
+            },
+        };
+        f.render_widget(output, chunks[1]);
+    }
+
+    // the value being edited replaces the input line in stack mode
+    let (prompt, input, cursor) = match &s.mode {
+        Mode::Stack { edit: Some(edit), .. } => ("e ", edit, true),
+        Mode::Stack { .. } => ("> ", &s.input, false),
+        Mode::Input | Mode::Vars | Mode::Help { .. } | Mode::Confirm(_) => ("> ", &s.input, true),
+    };
+
     // > prompt
-    let prompt = Paragraph::new("> ");
+    let prompt = Paragraph::new(prompt).style(s.theme.prompt);
     f.render_widget(prompt, input_chunks[0]);
 
+    if status_width > 0 {
+        f.render_widget(Paragraph::new(status).style(s.theme.status), input_chunks[2]);
+    }
+
     // input
-    let scroll = s.input.visual_scroll(input_chunks[1].width as usize - 1);
-    let input = Paragraph::new(s.input.value())
-        .scroll((0, scroll as u16));
-    f.render_widget(input, input_chunks[1]);
-    f.set_cursor(
-        input_chunks[1].x +
-            ((s.input.visual_cursor()).max(scroll) - scroll) as u16,
-        input_chunks[1].y);
+    let width = input_chunks[1].width as usize - 1;
+    let (value, cursor_x, start) = if input.value().len() > background::LONG_INPUT {
+        // only lay out the part around the cursor, a character per cell
+        let start = input.cursor().saturating_sub(width);
+        let shown = input.value().chars().skip(start).take(width + 1).collect::<String>();
+        (Paragraph::new(shown), input.cursor() - start, start)
+    } else {
+        let scroll = input.visual_scroll(width);
+        // the characters scrolled out of view, for placing clicks
+        let mut skipped = 0;
+        let start = input.value().chars().take_while(|c| {
+            skipped += c.width().unwrap_or(0);
+            skipped <= scroll
+        }).count();
+        (Paragraph::new(highlight(input.value(), err_span, &s.output, &s.theme)).scroll((0, scroll as u16)), input.visual_cursor().max(scroll) - scroll, start)
+    };
+    s.input_at.set((input_chunks[1], start));
+    f.render_widget(value, input_chunks[1]);
+    if cursor {
+        f.set_cursor(input_chunks[1].x + cursor_x as u16, input_chunks[1].y);
+    }
+    drawn[area.height as usize - 1] = if status_width > 0 { chunks[2].width as usize } else { (2 + input.value().chars().count()).min(width) };
+    s.drawn.replace(drawn);
+
+}
+
+/// The stack a value per row, for an output `rows` high and `width` wide:
+/// the top of the stack last, or first with `.top first`, and the level
+/// of each value in front of it, 1 for the top. The rows that don't fit
+/// are the bottom of the stack, or what is below the selected value in
+/// stack mode. Also whether each row is the selected one.
+fn stack_rows(state: &State, sel: Option<usize>, rows: usize, width: usize) -> Vec<(String, bool)> {
+    let depth = state.stack.len();
+    let level = depth.to_string().len();
+    let width = width.saturating_sub(level + 2);
+    // the bit ruler takes the row over the top
+    let ruler = state.bit_ruler().map(|ruler| (format!("{:2$}{}", "", fit(&ruler, width), level + 2), false));
+    let values = rows - ruler.is_some() as usize;
+    let start = depth.saturating_sub(values).min(sel.unwrap_or(depth));
+    let end = depth.min(start + values);
+    let mut rows = (start..end).map(|i| {
+        let value = fit(&state.render_value(state.stack[i]), width);
+        (format!("{:>level$}: {value}", depth - i), Some(i) == sel)
+    }).collect::<Vec<_>>();
+    if state.top_first {
+        rows.reverse();
+    }
+    if let Some(ruler) = ruler.filter(|_| end == depth) {
+        let at = if state.top_first { 0 } else { rows.len().saturating_sub(1) };
+        rows.insert(at, ruler);
+    }
+    rows
+}
+
+/// The bit positions of the top of the stack in `.bits`, lined up under
+/// it in an output line `width` wide.
+fn bit_ruler(state: &State, width: usize) -> Option<String> {
+    let ruler = state.bit_ruler()?;
+    Some(if state.top_first { ruler } else { format!("{ruler:>width$}") })
+}
+
+/// The input with each token colored by what it is, in the theme's colors,
+/// and the tokens of `err` underlined.
+fn highlight<'a>(text: &'a str, err: Option<std::ops::Range<usize>>, state: &State, theme: &Theme) -> Line<'a> {
+    let mut spans = vec![];
+    let mut end = 0;
+    for token in c::tokens(text, state) {
+        spans.push(Span::raw(&text[end..token.span.start]));
+        let mut style = theme.token(token.kind);
+        if err.as_ref().is_some_and(|err| err.start <= token.span.start && token.span.end <= err.end) {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        spans.push(Span::styled(&text[token.span.clone()], style));
+        end = token.span.end;
+    }
+    spans.push(Span::raw(&text[end..]));
+    Line::from(spans)
+}
+
+/// The `.help` listing in a box filling `area`, from line `scroll` on.
+fn help_popup(f: &mut Frame, scroll: usize, state: &State, area: Rect) {
+    let lines = help_lines(state);
+    // keep the last page in view however far it's scrolled
+    let scroll = scroll.min(lines.len().saturating_sub(area.height.saturating_sub(2) as usize));
+    let text = lines.into_iter().skip(scroll).map(Line::from).collect::<Vec<_>>();
+    let block = Block::default().borders(Borders::ALL).title("help").padding(Padding::horizontal(1));
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+/// The `.vars` listing in a box in the middle of `area`.
+fn vars_popup(f: &mut Frame, state: &State, area: Rect) {
+    let lines = state.vars_listing();
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 4;
+    let height = lines.len() as u16 + 2;
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width: width.min(area.width),
+        height: height.min(area.height),
+    };
 
+    f.render_widget(Clear, popup);
+    let text = lines.into_iter().map(Line::from).collect::<Vec<_>>();
+    let block = Block::default().borders(Borders::ALL).title("variables").padding(Padding::horizontal(1));
+    f.render_widget(Paragraph::new(text).block(block), popup);
 }
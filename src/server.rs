@@ -0,0 +1,116 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use serde_json::{json, Value as Json};
+
+use c::{eval, State, Value};
+use crate::sink::Sink;
+
+/// A stack value as a JSON number or boolean, `null` for NaN and
+/// infinities. Quantities are `{"value": 500, "unit": "MB"}` and subnets
+/// strings.
+pub fn value_json(x: Value) -> Json {
+    match x {
+        Value::Int(x) => x.into(),
+        Value::Float(x, _) => x.into(),
+        Value::Bool(b) => b.into(),
+        Value::Quantity(x, unit) => json!({ "value": x, "unit": unit.name }),
+        Value::Cidr(..) | Value::Ratio(..) => c::State::default().render_value(x).into(),
+    }
+}
+
+fn stack_json(state: &State) -> Json {
+    json!({
+        "stack": state.stack.iter().map(|&x| value_json(x)).collect::<Vec<_>>(),
+        "rendered": state.render().trim_end(),
+    })
+}
+
+/// `c --serve <socket>`: answer JSON-RPC 2.0 requests, one per line, on a
+/// unix socket. The state is shared by all connections and kept between
+/// requests.
+///
+/// Methods are `eval` (`{"expr": "1 2 +"}` or `["1 2 +"]`), which
+/// evaluates on top of the current stack and only keeps the result if
+/// evaluation succeeded, `stack` and `reset`.
+pub fn serve(path: &Path, mut state: State, sinks: &[Sink]) -> io::Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is being served already", path.display())));
+        }
+        // left behind by a server that didn't shut down cleanly
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let mut out = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = handle(&line, &mut state, sinks);
+            writeln!(out, "{response}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(line: &str, state: &mut State, sinks: &[Sink]) -> Json {
+    let request: Json = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error(Json::Null, -32700, &e.to_string(), None),
+    };
+    let id = request.get("id").cloned().unwrap_or(Json::Null);
+    let Some(method) = request.get("method").and_then(Json::as_str) else {
+        return error(id, -32600, "missing method", None);
+    };
+    let params = request.get("params").unwrap_or(&Json::Null);
+
+    let result = match method {
+        "eval" => {
+            let expr = params.get("expr").or(params.get(0)).and_then(Json::as_str);
+            let Some(expr) = expr else {
+                return error(id, -32602, "eval needs an expr", None);
+            };
+
+            let mut next = state.clone();
+            if let Err(e) = eval(expr, &mut next) {
+                return error(id, 1, &e.to_string(), Some(stack_json(&next)));
+            }
+            *state = next;
+            let saved = crate::session::run_effects(state);
+            if let Some(err) = crate::send(sinks, &state.render()).or(saved) {
+                eprintln!("{err}");
+            }
+            stack_json(state)
+        },
+        "stack" => stack_json(state),
+        "reset" => {
+            *state = State {
+                loader: state.loader,
+                clock: state.clock,
+                sandbox: state.sandbox,
+                constants: std::mem::take(&mut state.constants),
+                ..State::default()
+            };
+            stack_json(state)
+        },
+        _ => return error(id, -32601, &format!("unknown method '{method}'"), None),
+    };
+
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error(id: Json, code: i64, message: &str, data: Option<Json>) -> Json {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
@@ -0,0 +1,79 @@
+use std::io;
+use std::path::PathBuf;
+
+use c::{Effect, State};
+
+/// The session `--auto-session` restores on start and saves on exit.
+pub const AUTO: &str = "auto";
+
+/// `$XDG_DATA_HOME/c` (or `~/.local/share/c`).
+pub fn data_dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share"),
+    };
+    Some(dir.join("c"))
+}
+
+/// Sessions are kept as the input that rebuilds them, see
+/// [`State::to_input`], one file per name.
+fn path(name: &str) -> Result<PathBuf, String> {
+    if name.contains('/') || name.starts_with('.') {
+        return Err(format!("'{name}' isn't a session name"));
+    }
+    let dir = data_dir().ok_or("no data directory, set XDG_DATA_HOME or HOME")?;
+    Ok(dir.join("sessions").join(name))
+}
+
+pub fn exists(name: &str) -> bool {
+    path(name).is_ok_and(|path| path.exists())
+}
+
+/// Used as the [`State::loader`].
+pub fn load(name: &str) -> Result<String, String> {
+    let path = path(name)?;
+    std::fs::read_to_string(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => format!("no session '{name}'"),
+        _ => format!("{}: {e}", path.display()),
+    })
+}
+
+pub fn save(name: &str, input: &str) -> Result<(), String> {
+    let path = path(name)?;
+    let write = || {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, format!("{input}\n"))
+    };
+    write().map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Carry out the effects of a committed evaluation, returning the first
+/// failure.
+pub fn run_effects(state: &mut State) -> Option<String> {
+    let mut err = None;
+    for effect in state.effects.drain(..) {
+        let res = match effect {
+            Effect::Save { name, input } => save(&name, &input),
+            // up to the frontend, which has to look before this
+            Effect::ShowVars | Effect::ShowHelp | Effect::Discard(_) | Effect::Warn(_) | Effect::Trace(..) | Effect::Loaded => Ok(()),
+        };
+        if let Err(e) = res {
+            err.get_or_insert(e);
+        }
+    }
+    err
+}
+
+/// `--auto-session`: start from the session saved on the last exit, if
+/// there is one, returning whether there was. This is the user's own
+/// doing, so it works in the sandbox.
+pub fn restore_auto(state: &mut State) -> Result<bool, String> {
+    if !path(AUTO)?.exists() {
+        return Ok(false);
+    }
+    let sandbox = std::mem::take(&mut state.sandbox);
+    let res = c::eval(&format!(".load {AUTO}"), state).map(drop).map_err(|e| e.to_string());
+    state.sandbox = sandbox;
+    state.effects.clear();
+    res.map(|()| true)
+}
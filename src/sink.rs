@@ -0,0 +1,119 @@
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long an http sink gets to connect, and then for each read and
+/// write, before the commit gives up on it rather than hang.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Somewhere committed results are sent.
+///
+/// Written as `cmd:<shell command>` (result on stdin), `file:<path>`
+/// (appended as a line) or `http://host[:port][/path]` (POSTed as text).
+pub enum Sink {
+    Command(String),
+    File(PathBuf),
+    Http { addr: String, path: String },
+}
+
+impl FromStr for Sink {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Sink, String> {
+        if let Some(cmd) = spec.strip_prefix("cmd:") {
+            return Ok(Sink::Command(cmd.to_owned()));
+        }
+
+        if let Some(path) = spec.strip_prefix("file:") {
+            let path = match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+                (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                _ => PathBuf::from(path),
+            };
+            return Ok(Sink::File(path));
+        }
+
+        if let Some(url) = spec.strip_prefix("http://") {
+            let (addr, path) = match url.find('/') {
+                Some(i) => url.split_at(i),
+                None => (url, "/"),
+            };
+            if addr.is_empty() {
+                return Err(format!("sink '{spec}' has no host"));
+            }
+            let addr = if addr.contains(':') { addr.to_owned() } else { format!("{addr}:80") };
+            return Ok(Sink::Http { addr, path: path.to_owned() });
+        }
+
+        Err(format!("unknown sink '{spec}', expected cmd:, file: or http://"))
+    }
+}
+
+impl Sink {
+    pub fn send(&self, result: &str) -> io::Result<()> {
+        match self {
+            Sink::Command(cmd) => {
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(cmd)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                // take stdin so it is closed before waiting, otherwise
+                // commands like `xclip` never see end of input
+                child.stdin.take().unwrap().write_all(format!("{result}\n").as_bytes())?;
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(io::Error::other(format!("'{cmd}' exited with {status}")));
+                }
+            },
+            Sink::File(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{result}")?;
+            },
+            Sink::Http { addr, path } => {
+                let mut stream = connect(addr)?;
+                stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+                stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+                write!(stream,
+                    "POST {path} HTTP/1.1\r\n\
+                     Host: {addr}\r\n\
+                     Content-Type: text/plain\r\n\
+                     Content-Length: {}\r\n\
+                     Connection: close\r\n\r\n\
+                     {result}",
+                    result.len())?;
+
+                let mut response = String::new();
+                stream.read_to_string(&mut response).map_err(|e| match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                        io::Error::other(format!("{addr}{path}: no answer in {}s", HTTP_TIMEOUT.as_secs()))
+                    },
+                    _ => e,
+                })?;
+                let status = response.lines().next().unwrap_or_default();
+                if status.split_whitespace().nth(1).map_or(true, |code| !code.starts_with('2')) {
+                    return Err(io::Error::other(format!("{addr}{path}: {status}")));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Connect to the first address `addr` resolves to that answers in time.
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let mut err = io::Error::other(format!("{addr} resolves to no address"));
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, HTTP_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => err = e,
+        }
+    }
+    Err(err)
+}
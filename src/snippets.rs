@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Expressions kept by name in `$XDG_CONFIG_HOME/c/snippets`, a
+/// `name = expression` line each.
+pub type Snippets = BTreeMap<String, String>;
+
+fn path() -> Option<PathBuf> {
+    Some(crate::config::dir()?.join("snippets"))
+}
+
+pub fn load() -> Result<Snippets, String> {
+    let Some(path) = path() else {
+        return Ok(Snippets::new());
+    };
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Snippets::new()),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+
+    let mut snippets = Snippets::new();
+    for (n, line) in src.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((name, expr)) = line.split_once('=') else {
+            return Err(format!("{}: line {}: expected 'name = expression'", path.display(), n + 1));
+        };
+        snippets.insert(name.trim().to_owned(), expr.trim().to_owned());
+    }
+    Ok(snippets)
+}
+
+/// Add or replace a snippet and write them all back.
+pub fn save(snippets: &mut Snippets, name: &str, expr: &str) -> Result<(), String> {
+    if name.contains('=') {
+        return Err(format!("'{name}' isn't a snippet name"));
+    }
+    snippets.insert(name.to_owned(), expr.to_owned());
+
+    let path = path().ok_or("no config directory, set XDG_CONFIG_HOME or HOME")?;
+    let src = snippets.iter().map(|(name, expr)| format!("{name} = {expr}\n")).collect::<Vec<_>>().concat();
+    let write = || {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(&path, src)
+    };
+    write().map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The names `query` fuzzily matches, best first: its characters have to
+/// appear in order, and the fewer characters skipped the better.
+pub fn find<'a>(snippets: &'a Snippets, query: &str) -> Vec<&'a str> {
+    let skipped = |name: &str| {
+        let mut chars = name.chars();
+        let mut skipped = 0;
+        for q in query.chars() {
+            loop {
+                match chars.next() {
+                    Some(c) if c == q => break,
+                    Some(_) => skipped += 1,
+                    None => return None,
+                }
+            }
+        }
+        Some(skipped)
+    };
+
+    let mut found = snippets.keys()
+        .filter_map(|name| Some((skipped(name)?, name.len(), name.as_str())))
+        .collect::<Vec<_>>();
+    found.sort();
+    found.into_iter().map(|(.., name)| name).collect()
+}
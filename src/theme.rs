@@ -0,0 +1,137 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use c::TokenKind;
+
+/// The colors of the TUI, changed with `color.<part> = <color>` lines in
+/// the config, like `color.error = light red` or `color.number = #5f87af`.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub error: Style,
+    pub result: Style,
+    pub prompt: Style,
+    /// The selected stack value or completion.
+    pub highlight: Style,
+    /// The display format, word size and depth by the prompt.
+    pub status: Style,
+    number: Style,
+    operator: Style,
+    command: Style,
+    argument: Style,
+    variable: Style,
+    unknown: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let fg = |color| Style::default().fg(color);
+        Theme {
+            error: fg(Color::Red),
+            result: Style::default(),
+            prompt: Style::default(),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            status: fg(Color::DarkGray),
+            number: fg(Color::Cyan),
+            operator: fg(Color::Yellow),
+            command: fg(Color::Magenta),
+            argument: fg(Color::Green),
+            variable: fg(Color::Blue),
+            unknown: fg(Color::Red),
+        }
+    }
+}
+
+const PARTS: &[&str] = &[
+    "error", "result", "prompt", "highlight", "status",
+    "number", "operator", "command", "argument", "variable", "unknown",
+];
+
+impl Theme {
+    /// For `--no-color` and `NO_COLOR`: the highlight still stands out,
+    /// in reverse video.
+    pub fn mono() -> Theme {
+        let plain = Style::default();
+        Theme {
+            error: plain,
+            result: plain,
+            prompt: plain,
+            highlight: plain.add_modifier(Modifier::REVERSED),
+            status: plain,
+            number: plain,
+            operator: plain,
+            command: plain,
+            argument: plain,
+            variable: plain,
+            unknown: plain,
+        }
+    }
+
+    pub fn set(&mut self, part: &str, color: &str) -> Result<(), String> {
+        let style = match part {
+            "error" => &mut self.error,
+            "result" => &mut self.result,
+            "prompt" => &mut self.prompt,
+            "highlight" => {
+                // a color for the highlight is its background
+                self.highlight = Style::default().bg(parse_color(color)?);
+                return Ok(());
+            },
+            "status" => &mut self.status,
+            "number" => &mut self.number,
+            "operator" => &mut self.operator,
+            "command" => &mut self.command,
+            "argument" => &mut self.argument,
+            "variable" => &mut self.variable,
+            "unknown" => &mut self.unknown,
+            _ => return Err(format!("nothing called '{part}' to color, expected one of {}", PARTS.join(", "))),
+        };
+        *style = Style::default().fg(parse_color(color)?);
+        Ok(())
+    }
+
+    pub fn token(&self, kind: TokenKind) -> Style {
+        match kind {
+            TokenKind::Number | TokenKind::Unit => self.number,
+            TokenKind::Operator => self.operator,
+            TokenKind::Command => self.command,
+            TokenKind::Argument => self.argument,
+            TokenKind::Variable | TokenKind::Result => self.variable,
+            TokenKind::Unknown => self.unknown,
+        }
+    }
+}
+
+/// Whether the `NO_COLOR` convention asks for no colors.
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// `red`, `light red`, `#5f87af`, an index like `67`, or `none`.
+fn parse_color(color: &str) -> Result<Color, String> {
+    if let Some(hex) = color.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| format!("unknown color '{color}'"))?;
+        return Ok(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+    if let Ok(index) = color.parse() {
+        return Ok(Color::Indexed(index));
+    }
+    Ok(match color.split_whitespace().collect::<Vec<_>>()[..] {
+        ["none"] => Color::Reset,
+        ["black"] => Color::Black,
+        ["red"] => Color::Red,
+        ["green"] => Color::Green,
+        ["yellow"] => Color::Yellow,
+        ["blue"] => Color::Blue,
+        ["magenta"] => Color::Magenta,
+        ["cyan"] => Color::Cyan,
+        ["gray"] => Color::Gray,
+        ["dark", "gray"] => Color::DarkGray,
+        ["light", "red"] => Color::LightRed,
+        ["light", "green"] => Color::LightGreen,
+        ["light", "yellow"] => Color::LightYellow,
+        ["light", "blue"] => Color::LightBlue,
+        ["light", "magenta"] => Color::LightMagenta,
+        ["light", "cyan"] => Color::LightCyan,
+        ["white"] => Color::White,
+        _ => return Err(format!("unknown color '{color}'")),
+    })
+}
@@ -0,0 +1,79 @@
+/// A step of `c tutor`: what to read, and the output that shows the
+/// exercise was done.
+pub struct Lesson {
+    pub text: &'static [&'static str],
+    /// The rendered stack to get to, `None` to just read and press Enter.
+    pub expect: Option<&'static str>,
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        text: &[
+            "c is an RPN calculator: numbers go on a stack, and words like + take",
+            "their arguments off the top of it. The line is evaluated as you type.",
+            "Type `2 3 +` and press Enter.",
+        ],
+        expect: Some("5"),
+    },
+    Lesson {
+        text: &[
+            "`p` is another name for `+`, and `*` or `m` multiplies. There are no",
+            "parentheses, the order of the words is the order things happen in.",
+            "Work out (2 + 3) * 4.",
+        ],
+        expect: Some("20"),
+    },
+    Lesson {
+        text: &[
+            "Numbers stay on the stack until a word uses them, the top is on the",
+            "right. `d` divides, and integers divide to integers: `7 2 d` is 3.",
+            "With a decimal point they're floats. Work out 1 divided by 4.",
+        ],
+        expect: Some("0.25"),
+    },
+    Lesson {
+        text: &[
+            "`/op` folds the whole stack with an operator, and `n i` pushes the",
+            "numbers 1 to n. Add up 1 to 10.",
+        ],
+        expect: Some("55"),
+    },
+    Lesson {
+        text: &[
+            "Writing a `0x` number switches the display to hex, `.d` switches it",
+            "back to decimal and `.h` to hex again. What is 0xff in decimal?",
+        ],
+        expect: Some("255"),
+    },
+    Lesson {
+        text: &[
+            "Times are written as `h:mm`, which are minutes underneath, and",
+            "`sumtime` adds up the stack as a time. Add 1:30 and 2:45.",
+        ],
+        expect: Some("4:15"),
+    },
+    Lesson {
+        text: &[
+            "`>name` pops the top into a variable and `$name` pushes it again.",
+            "Variables last until you quit, on the following lines too.",
+            "Put 12 in `rate`, then work out rate times 3.",
+        ],
+        expect: Some("36"),
+    },
+    Lesson {
+        text: &[
+            "Each line you commit is numbered, the @n in front of its result.",
+            "`@n` pushes the top of result n again, and `@n*` all of it. Halve",
+            "the result of the last line.",
+        ],
+        expect: Some("18"),
+    },
+    Lesson {
+        text: &[
+            "That's the basics. Up and Down bring back earlier lines, Tab",
+            "completes a word, Ctrl-S edits the stack and Ctrl-Y copies the",
+            "result. F1 or `.help` lists every word. Press Enter to finish.",
+        ],
+        expect: None,
+    },
+];
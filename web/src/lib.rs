@@ -0,0 +1,21 @@
+//! The evaluator compiled to wasm for `index.html`.
+//!
+//! ```sh
+//! cargo build -p c-web --release --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir web/pkg target/wasm32-unknown-unknown/release/c_web.wasm
+//! ```
+//!
+//! and serve the `web` directory, e.g. with `python3 -m http.server -d web`.
+
+use wasm_bindgen::prelude::*;
+
+/// Evaluate `input` on an empty stack. The result is the rendered stack,
+/// followed by a second line with the error if evaluation failed.
+#[wasm_bindgen]
+pub fn eval_to_string(input: &str) -> String {
+    let mut state = c::State::default();
+    match c::eval(input, &mut state) {
+        Ok(_) => state.render(),
+        Err(e) => format!("{}\n{e}", state.render()),
+    }
+}